@@ -0,0 +1,996 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use pest::iterators::{Pair, Pairs};
+
+use crate::type_string::make_string;
+use crate::variables::{format_struct, format_variant, VariableContent, VariableTypes};
+use crate::Rule;
+
+// Where a node was found in the source code.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize, // Byte offsets into the source.
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    fn from_pair(pair: &Pair<Rule>) -> Span {
+        let span = pair.as_span();
+        let (line, column) = span.start_pos().line_col();
+        Span {
+            start: span.start(),
+            end: span.end(),
+            line,
+            column,
+        }
+    }
+}
+
+// Where the resolver found a variable: how many scopes up from the current one, and its index there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slot {
+    pub depth: usize,
+    pub index: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum Expression {
+    Literal {
+        value: VariableContent,
+        span: Span,
+    },
+    Variable {
+        name: String,
+        slot: Cell<Option<Slot>>, // Filled in by the resolver.
+        span: Span,
+    },
+    Call {
+        namespace: Option<String>, // The module of calls like utils.helper().
+        name: String,
+        args: Vec<Expression>,
+        keywords: Vec<KeywordArg>, // Always after the positional arguments.
+        // Set when the name is a variable holding a function, or to the module of the namespace.
+        slot: Cell<Option<Slot>>,
+        span: Span,
+    },
+    Function(Rc<FunctionDecl>),
+    List {
+        items: Vec<Expression>,
+        span: Span,
+    },
+    Struct {
+        name: String,
+        fields: Vec<FieldValue>, // In the order they are written.
+        decl: RefCell<Option<Rc<StructDecl>>>, // Filled in by the resolver.
+        span: Span,
+    },
+    Field {
+        value: Box<Expression>,
+        field: String,
+        span: Span,
+    },
+    Variant {
+        enum_name: String,
+        variant: String,
+        args: Vec<Expression>,
+        decl: RefCell<Option<Rc<EnumDecl>>>, // Filled in by the resolver.
+        span: Span,
+    },
+}
+
+impl Expression {
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Literal { span, .. }
+            | Expression::Variable { span, .. }
+            | Expression::Call { span, .. }
+            | Expression::List { span, .. }
+            | Expression::Struct { span, .. }
+            | Expression::Field { span, .. }
+            | Expression::Variant { span, .. } => *span,
+            Expression::Function(decl) => decl.span,
+        }
+    }
+}
+
+// A field given a value when a struct is built.
+#[derive(Debug, Clone)]
+pub struct FieldValue {
+    pub name: String,
+    pub value: Expression,
+    pub span: Span,
+}
+
+// An argument passed by the name of the parameter it is for.
+#[derive(Debug, Clone)]
+pub struct KeywordArg {
+    pub name: String,
+    pub value: Expression,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum Statement {
+    // Also used for constants, an empty declaration has no value.
+    VarDecl {
+        name: String,
+        annotation: Option<VariableTypes>,
+        constant: bool,
+        value: Option<Expression>,
+        index: Cell<Option<usize>>, // Declarations always go into the current scope.
+        span: Span,
+    },
+    Assign {
+        name: String,
+        value: Expression,
+        slot: Cell<Option<Slot>>,
+        span: Span,
+    },
+    // Sets a field of the struct in a variable, the path leads through nested structs.
+    FieldAssign {
+        name: String,
+        path: Vec<String>,
+        value: Expression,
+        slot: Cell<Option<Slot>>,
+        span: Span,
+    },
+    FunctionDecl(Rc<FunctionDecl>),
+    StructDecl(Rc<StructDecl>),
+    EnumDecl(Rc<EnumDecl>),
+    Return {
+        value: Option<Expression>,
+        span: Span,
+    },
+    If {
+        condition: Expression,
+        body: Vec<Statement>,
+        span: Span,
+    },
+    // Runs the first arm whose pattern matches the value.
+    Match {
+        value: Expression,
+        arms: Vec<MatchArm>,
+        span: Span,
+    },
+    // Raises the value as an error.
+    Throw {
+        value: Expression,
+        span: Span,
+    },
+    // An error in the body runs the catch block, the finally block runs after both.
+    Try {
+        body: Vec<Statement>,
+        catch: Option<CatchClause>,
+        finally: Option<Vec<Statement>>,
+        span: Span,
+    },
+    Expression(Expression),
+    // Runs the file the first time it is imported and declares a constant with its module.
+    Import {
+        path: String, // As written, relative to the importing file.
+        name: String,
+        module: Cell<Option<usize>>, // The index of the file, set when it is loaded.
+        index: Cell<Option<usize>>,
+        span: Span,
+    },
+    // Lets the file call the functions of a builtin module without naming the module.
+    Use {
+        module: String,
+        span: Span,
+    },
+    DebugVar {
+        span: Span,
+    },
+}
+
+impl Statement {
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::VarDecl { span, .. }
+            | Statement::Assign { span, .. }
+            | Statement::FieldAssign { span, .. }
+            | Statement::Return { span, .. }
+            | Statement::If { span, .. }
+            | Statement::Match { span, .. }
+            | Statement::Throw { span, .. }
+            | Statement::Try { span, .. }
+            | Statement::Import { span, .. }
+            | Statement::Use { span, .. }
+            | Statement::DebugVar { span } => *span,
+            Statement::FunctionDecl(decl) => decl.span,
+            Statement::StructDecl(decl) => decl.span,
+            Statement::EnumDecl(decl) => decl.span,
+            Statement::Expression(expression) => expression.span(),
+        }
+    }
+}
+
+// The body of a catch block gets its own scope, which starts with the error.
+#[derive(Debug, Clone)]
+pub struct CatchClause {
+    pub name: String,
+    pub body: Vec<Statement>,
+    pub span: Span,
+}
+
+// A function declared in the script, shared between the program and the FunctionContainer.
+// Function literals have no name of their own.
+#[derive(Debug)]
+pub struct FunctionDecl {
+    pub name: String,
+    pub params: Vec<Param>,
+    pub rest: Option<String>, // Collects the extra positional arguments into a list.
+    pub return_type: Option<VariableTypes>,
+    pub body: Vec<Statement>,
+    pub span: Span,
+}
+
+impl FunctionDecl {
+    // The names shown in the value of a function, with the rest parameter last.
+    pub fn param_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.params.iter().map(|p| p.name.clone()).collect();
+        if let Some(rest) = &self.rest {
+            names.push(format!("...{}", rest));
+        }
+        names
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: String,
+    pub annotation: Option<VariableTypes>,
+    pub default: Option<Expression>, // Evaluated when the call doesn't give the argument.
+}
+
+// Structs can be used anywhere in the script, no matter where they are declared.
+#[derive(Debug)]
+pub struct StructDecl {
+    pub name: String,
+    pub fields: Vec<StructField>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct StructField {
+    pub name: String,
+    pub annotation: Option<VariableTypes>,
+}
+
+// Enums are declared at the top level like structs, a variant can hold values.
+#[derive(Debug)]
+pub struct EnumDecl {
+    pub name: String,
+    pub variants: Vec<EnumVariant>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnumVariant {
+    pub name: String,
+    pub fields: Vec<String>, // Only the number of them matters, the names document them.
+}
+
+// The body of an arm gets its own scope, which starts with the bindings of the pattern.
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Vec<Statement>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Wildcard,
+    Literal(VariableContent),
+    Binding(String),
+    Variant {
+        enum_name: String,
+        variant: String,
+        items: Vec<Pattern>,
+        decl: RefCell<Option<Rc<EnumDecl>>>, // Filled in by the resolver.
+        span: Span,
+    },
+    // The rest binds the items after the ones matched by the patterns.
+    List {
+        items: Vec<Pattern>,
+        rest: Option<String>,
+    },
+}
+
+impl Pattern {
+    // The names the pattern binds, in the order they get their index in the scope.
+    pub fn bindings(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        self.collect_bindings(&mut names);
+        names
+    }
+
+    fn collect_bindings(&self, names: &mut Vec<String>) {
+        match self {
+            Pattern::Wildcard | Pattern::Literal(_) => {}
+            Pattern::Binding(name) => names.push(name.clone()),
+            Pattern::Variant { items, .. } => {
+                items.iter().for_each(|item| item.collect_bindings(names))
+            }
+            Pattern::List { items, rest } => {
+                items.iter().for_each(|item| item.collect_bindings(names));
+                names.extend(rest.clone());
+            }
+        }
+    }
+
+    // Whether the pattern matches every value.
+    pub fn irrefutable(&self) -> bool {
+        matches!(self, Pattern::Wildcard | Pattern::Binding(_))
+    }
+}
+
+// Turns the parsed file into the statements of the program.
+pub fn build_ast(pairs: Pairs<Rule>) -> Vec<Statement> {
+    build_lines(pairs)
+}
+
+// Every line holds at most one statement, the EOI and empty lines are skipped.
+fn build_lines<'a>(lines: impl Iterator<Item = Pair<'a, Rule>>) -> Vec<Statement> {
+    lines
+        .filter(|line| line.as_rule() == Rule::line)
+        .flat_map(|line| line.into_inner())
+        .map(build_statement)
+        .collect()
+}
+
+fn build_annotation(pair: Pair<Rule>) -> VariableTypes {
+    VariableTypes::from_annotation(pair.as_str())
+}
+
+fn build_statement(pair: Pair<Rule>) -> Statement {
+    let span = Span::from_pair(&pair);
+    match pair.as_rule() {
+        Rule::var_decl_assign | Rule::var_empty_decl | Rule::const_decl => {
+            let constant = pair.as_rule() == Rule::const_decl;
+            let mut name = "";
+            let mut annotation = None;
+            let mut value = None;
+            for info in pair.into_inner() {
+                match info.as_rule() {
+                    Rule::var_name => name = info.as_str(),
+                    Rule::type_annotation => annotation = Some(build_annotation(info)),
+                    Rule::var_types => value = Some(build_var_types(info)),
+                    _ => panic!("Not implemented: {}", info),
+                }
+            }
+            Statement::VarDecl {
+                name: name.to_string(),
+                annotation,
+                constant,
+                value,
+                index: Cell::new(None),
+                span,
+            }
+        }
+        Rule::var_reassign_decl => {
+            let mut inner = pair.into_inner();
+            let name = inner.next().unwrap().as_str().to_string();
+            let value = build_var_types(inner.next().unwrap());
+            Statement::Assign {
+                name,
+                value,
+                slot: Cell::new(None),
+                span,
+            }
+        }
+        Rule::field_assign_decl => {
+            let mut inner = pair.into_inner();
+            let mut path: Vec<String> = inner
+                .next()
+                .unwrap()
+                .into_inner()
+                .map(|name| name.as_str().to_string())
+                .collect();
+            let name = path.remove(0);
+            Statement::FieldAssign {
+                name,
+                path,
+                value: build_var_types(inner.next().unwrap()),
+                slot: Cell::new(None),
+                span,
+            }
+        }
+        Rule::func_decl => Statement::FunctionDecl(Rc::new(build_function_decl(pair))),
+        Rule::struct_decl => {
+            let mut inner = pair.into_inner();
+            let name = inner.next().unwrap().as_str().to_string();
+            let fields = inner
+                .map(|field| {
+                    let mut field_inner = field.into_inner();
+                    StructField {
+                        name: field_inner.next().unwrap().as_str().to_string(),
+                        annotation: field_inner.next().map(build_annotation),
+                    }
+                })
+                .collect();
+            Statement::StructDecl(Rc::new(StructDecl { name, fields, span }))
+        }
+        Rule::return_decl => Statement::Return {
+            value: pair.into_inner().next().map(build_var_types),
+            span,
+        },
+        Rule::control_if => {
+            // The first pair is the condition, the rest are the lines of the body.
+            let mut inner = pair.into_inner();
+            let condition = build_expression(inner.next().unwrap());
+            Statement::If {
+                condition,
+                body: build_lines(inner),
+                span,
+            }
+        }
+        Rule::enum_decl => {
+            let mut inner = pair.into_inner();
+            let name = inner.next().unwrap().as_str().to_string();
+            let variants = inner
+                .map(|variant| {
+                    let mut names = variant.into_inner().map(|name| name.as_str().to_string());
+                    EnumVariant {
+                        name: names.next().unwrap(),
+                        fields: names.collect(),
+                    }
+                })
+                .collect();
+            Statement::EnumDecl(Rc::new(EnumDecl {
+                name,
+                variants,
+                span,
+            }))
+        }
+        Rule::control_match => {
+            let mut inner = pair.into_inner();
+            let value = build_expression(inner.next().unwrap());
+            let arms = inner
+                .map(|arm| {
+                    let arm_span = Span::from_pair(&arm);
+                    let mut arm_inner = arm.into_inner();
+                    MatchArm {
+                        pattern: build_pattern(arm_inner.next().unwrap()),
+                        body: build_lines(arm_inner),
+                        span: arm_span,
+                    }
+                })
+                .collect();
+            Statement::Match { value, arms, span }
+        }
+        Rule::throw_decl => Statement::Throw {
+            value: build_var_types(pair.into_inner().next().unwrap()),
+            span,
+        },
+        Rule::control_try => {
+            let mut body = Vec::new();
+            let mut catch = None;
+            let mut finally = None;
+            for inner in pair.into_inner() {
+                match inner.as_rule() {
+                    Rule::line => body.extend(inner.into_inner().map(build_statement)),
+                    Rule::catch_clause => {
+                        let catch_span = Span::from_pair(&inner);
+                        let mut catch_inner = inner.into_inner();
+                        catch = Some(CatchClause {
+                            name: catch_inner.next().unwrap().as_str().to_string(),
+                            body: build_lines(catch_inner),
+                            span: catch_span,
+                        });
+                    }
+                    Rule::finally_clause => finally = Some(build_lines(inner.into_inner())),
+                    _ => panic!("Not implemented: {}", inner),
+                }
+            }
+            Statement::Try {
+                body,
+                catch,
+                finally,
+                span,
+            }
+        }
+        Rule::func_call_decl => Statement::Expression(build_expression(pair)),
+        Rule::import_decl => {
+            let mut inner = pair.into_inner();
+            Statement::Import {
+                path: make_string(inner.next().unwrap().as_str()),
+                name: inner.next().unwrap().as_str().to_string(),
+                module: Cell::new(None),
+                index: Cell::new(None),
+                span,
+            }
+        }
+        Rule::use_decl => Statement::Use {
+            module: pair.into_inner().next().unwrap().as_str().to_string(),
+            span,
+        },
+        Rule::debug => {
+            let debug_what = pair.into_inner().next().unwrap().as_rule();
+            match debug_what {
+                Rule::debug_var => Statement::DebugVar { span },
+                _ => panic!("Unsupported debug rule '{:?}'.", debug_what),
+            }
+        }
+        _ => panic!("Rule '{:?}' not implemented.", pair.as_rule()),
+    }
+}
+
+// The name of function literals in error messages.
+pub const ANONYMOUS_FUNCTION: &str = "<fn>";
+
+fn build_function_decl(pair: Pair<Rule>) -> FunctionDecl {
+    let span = Span::from_pair(&pair);
+    let literal = pair.as_rule() == Rule::func_literal;
+    let mut inner = pair.into_inner().peekable();
+    let name = match literal {
+        true => ANONYMOUS_FUNCTION.to_string(),
+        false => inner.next().unwrap().as_str().to_string(),
+    };
+
+    // Gets the names, optional types and default values of the parameters.
+    let mut params = Vec::new();
+    let mut rest = None;
+    for param in inner.next().unwrap().into_inner() {
+        if param.as_rule() == Rule::func_rest_param {
+            rest = Some(param.into_inner().next().unwrap().as_str().to_string());
+            continue;
+        }
+        let mut param_inner = param.into_inner();
+        let mut param = Param {
+            name: param_inner.next().unwrap().as_str().to_string(),
+            annotation: None,
+            default: None,
+        };
+        for info in param_inner {
+            match info.as_rule() {
+                Rule::type_annotation => param.annotation = Some(build_annotation(info)),
+                _ => param.default = Some(build_expression(info)),
+            }
+        }
+        params.push(param);
+    }
+
+    // The return type is optional, everything after it is the body.
+    let mut return_type = None;
+    if inner.peek().map(|p| p.as_rule()) == Some(Rule::func_return_type) {
+        let annotation = inner.next().unwrap().into_inner().next().unwrap();
+        return_type = Some(build_annotation(annotation));
+    }
+
+    FunctionDecl {
+        name,
+        params,
+        rest,
+        return_type,
+        body: build_lines(inner),
+        span,
+    }
+}
+
+// The var_types rule only wraps the actual value.
+fn build_var_types(pair: Pair<Rule>) -> Expression {
+    build_expression(pair.into_inner().next().expect("Error parsing type."))
+}
+
+// Builds a literal, variable or function call.
+fn build_expression(pair: Pair<Rule>) -> Expression {
+    let span = Span::from_pair(&pair);
+    let literal = |data_type, value| Expression::Literal {
+        value: VariableContent::new(value, data_type),
+        span,
+    };
+    match pair.as_rule() {
+        Rule::type_int => literal(VariableTypes::INT, pair.as_str().to_string()),
+        Rule::type_bool => literal(VariableTypes::BOOL, pair.as_str().to_string()),
+        Rule::type_float => literal(VariableTypes::FLOAT, pair.as_str().to_string()),
+        Rule::type_string => literal(VariableTypes::STRING, make_string(pair.as_str())),
+        Rule::var_name => Expression::Variable {
+            name: pair.as_str().to_string(),
+            slot: Cell::new(None),
+            span,
+        },
+        Rule::func_call_decl => {
+            let mut namespace = None;
+            let mut name = "";
+            let mut args = Vec::new();
+            let mut keywords = Vec::new();
+            for p in pair.into_inner() {
+                match p.as_rule() {
+                    Rule::func_namespace => namespace = Some(p.as_str().to_string()),
+                    Rule::func_call_name => name = p.as_str(),
+                    Rule::func_call_args => {
+                        for arg in p.into_inner() {
+                            match arg.as_rule() {
+                                Rule::func_keyword_arg => keywords.push(build_keyword_arg(arg)),
+                                _ => args.push(build_expression(arg)),
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Expression::Call {
+                namespace,
+                name: name.to_string(),
+                args,
+                keywords,
+                slot: Cell::new(None),
+                span,
+            }
+        }
+        Rule::func_literal => Expression::Function(Rc::new(build_function_decl(pair))),
+        Rule::list_literal => Expression::List {
+            items: pair.into_inner().map(build_expression).collect(),
+            span,
+        },
+        Rule::struct_literal => {
+            let mut inner = pair.into_inner();
+            let name = inner.next().unwrap().as_str().to_string();
+            let fields = inner
+                .map(|field| {
+                    let field_span = Span::from_pair(&field);
+                    let mut field_inner = field.into_inner();
+                    FieldValue {
+                        name: field_inner.next().unwrap().as_str().to_string(),
+                        value: build_expression(field_inner.next().unwrap()),
+                        span: field_span,
+                    }
+                })
+                .collect();
+            Expression::Struct {
+                name,
+                fields,
+                decl: RefCell::new(None),
+                span,
+            }
+        }
+        Rule::variant_literal => {
+            let mut inner = pair.into_inner();
+            Expression::Variant {
+                enum_name: inner.next().unwrap().as_str().to_string(),
+                variant: inner.next().unwrap().as_str().to_string(),
+                args: inner.map(build_expression).collect(),
+                decl: RefCell::new(None),
+                span,
+            }
+        }
+        // Every field goes one struct deeper, the span ends after that field.
+        Rule::field_access => {
+            let mut inner = pair.into_inner();
+            let mut expression = build_expression(inner.next().unwrap());
+            for field in inner {
+                let field_span = Span::from_pair(&field);
+                expression = Expression::Field {
+                    value: Box::new(expression),
+                    field: field.as_str().to_string(),
+                    span: Span {
+                        end: field_span.end,
+                        ..span
+                    },
+                };
+            }
+            expression
+        }
+        _ => panic!("Type not implemented: {:?}", pair.as_rule()),
+    }
+}
+
+fn build_pattern(pair: Pair<Rule>) -> Pattern {
+    let span = Span::from_pair(&pair);
+    match pair.as_rule() {
+        Rule::wildcard_pattern => Pattern::Wildcard,
+        Rule::binding_pattern => Pattern::Binding(pair.as_str().to_string()),
+        Rule::variant_pattern => {
+            let mut inner = pair.into_inner();
+            Pattern::Variant {
+                enum_name: inner.next().unwrap().as_str().to_string(),
+                variant: inner.next().unwrap().as_str().to_string(),
+                items: inner.map(build_pattern).collect(),
+                decl: RefCell::new(None),
+                span,
+            }
+        }
+        Rule::list_pattern => {
+            let mut items = Vec::new();
+            let mut rest = None;
+            for item in pair.into_inner() {
+                match item.as_rule() {
+                    Rule::rest_pattern => {
+                        rest = Some(item.into_inner().next().unwrap().as_str().to_string())
+                    }
+                    _ => items.push(build_pattern(item)),
+                }
+            }
+            Pattern::List { items, rest }
+        }
+        // The other patterns are literals.
+        _ => match build_expression(pair) {
+            Expression::Literal { value, .. } => Pattern::Literal(value),
+            _ => unreachable!("Only literals are left."),
+        },
+    }
+}
+
+fn build_keyword_arg(pair: Pair<Rule>) -> KeywordArg {
+    let span = Span::from_pair(&pair);
+    let mut inner = pair.into_inner();
+    KeywordArg {
+        name: inner.next().unwrap().as_str().to_string(),
+        value: build_expression(inner.next().unwrap()),
+        span,
+    }
+}
+
+// Writes the program back as source code, used to show what the optimizer did.
+pub fn format_program(statements: &[Statement]) -> String {
+    let mut output = String::new();
+    format_lines(statements, 0, &mut output);
+    output
+}
+
+fn format_lines(statements: &[Statement], indent: usize, output: &mut String) {
+    for statement in statements {
+        output.push_str(&"    ".repeat(indent));
+        format_statement(statement, indent, output);
+        output.push('\n');
+    }
+}
+
+fn format_block(statements: &[Statement], indent: usize, output: &mut String) {
+    output.push_str("{\n");
+    format_lines(statements, indent + 1, output);
+    output.push_str(&"    ".repeat(indent));
+    output.push('}');
+}
+
+fn format_annotation(annotation: VariableTypes) -> String {
+    format!("{:?}", annotation).to_lowercase()
+}
+
+fn format_statement(statement: &Statement, indent: usize, output: &mut String) {
+    match statement {
+        Statement::VarDecl {
+            name,
+            annotation,
+            constant,
+            value,
+            ..
+        } => {
+            output.push_str(if *constant { "const " } else { "var " });
+            output.push_str(name);
+            if let Some(annotation) = annotation {
+                output.push_str(&format!(": {}", format_annotation(*annotation)));
+            }
+            if let Some(value) = value {
+                output.push_str(&format!(" = {}", format_expression(value, indent)));
+            }
+        }
+        Statement::Assign { name, value, .. } => {
+            output.push_str(&format!("{} = {}", name, format_expression(value, indent)));
+        }
+        Statement::FieldAssign {
+            name, path, value, ..
+        } => {
+            let value = format_expression(value, indent);
+            output.push_str(&format!("{}.{} = {}", name, path.join("."), value));
+        }
+        Statement::FunctionDecl(decl) => format_function(decl, indent, output),
+        Statement::EnumDecl(decl) => {
+            let variants: Vec<String> = decl
+                .variants
+                .iter()
+                .map(|variant| match variant.fields.is_empty() {
+                    true => variant.name.clone(),
+                    false => format!("{}({})", variant.name, variant.fields.join(", ")),
+                })
+                .collect();
+            output.push_str(&format!("enum {}", format_struct(&decl.name, &variants)));
+        }
+        Statement::Match { value, arms, .. } => {
+            output.push_str(&format!("match {} {{\n", format_expression(value, indent)));
+            for arm in arms {
+                output.push_str(&"    ".repeat(indent + 1));
+                output.push_str(&format!("{} => ", format_pattern(&arm.pattern)));
+                format_block(&arm.body, indent + 1, output);
+                output.push('\n');
+            }
+            output.push_str(&"    ".repeat(indent));
+            output.push('}');
+        }
+        Statement::StructDecl(decl) => {
+            let fields: Vec<String> = decl
+                .fields
+                .iter()
+                .map(|field| match field.annotation {
+                    Some(annotation) => {
+                        format!("{}: {}", field.name, format_annotation(annotation))
+                    }
+                    None => field.name.clone(),
+                })
+                .collect();
+            output.push_str(&format!("struct {}", format_struct(&decl.name, &fields)));
+        }
+        Statement::Return { value, .. } => {
+            output.push_str("return");
+            if let Some(value) = value {
+                output.push_str(&format!(" {}", format_expression(value, indent)));
+            }
+        }
+        Statement::If {
+            condition, body, ..
+        } => {
+            output.push_str(&format!("if {} ", format_expression(condition, indent)));
+            format_block(body, indent, output);
+        }
+        Statement::Throw { value, .. } => {
+            output.push_str(&format!("throw {}", format_expression(value, indent)));
+        }
+        Statement::Try {
+            body,
+            catch,
+            finally,
+            ..
+        } => {
+            output.push_str("try ");
+            format_block(body, indent, output);
+            if let Some(catch) = catch {
+                output.push_str(&format!(" catch {} ", catch.name));
+                format_block(&catch.body, indent, output);
+            }
+            if let Some(finally) = finally {
+                output.push_str(" finally ");
+                format_block(finally, indent, output);
+            }
+        }
+        Statement::Expression(expression) => {
+            output.push_str(&format_expression(expression, indent))
+        }
+        Statement::Import { path, name, .. } => {
+            let path = format_literal(&VariableContent::new(path.clone(), VariableTypes::STRING));
+            output.push_str(&format!("import {} as {}", path, name));
+        }
+        Statement::Use { module, .. } => output.push_str(&format!("use {}", module)),
+        Statement::DebugVar { .. } => output.push_str("debug.var"),
+    }
+}
+
+fn format_literal(value: &VariableContent) -> String {
+    match value.data_type {
+        VariableTypes::STRING => format!(
+            "\"{}\"",
+            value
+                .value
+                .replace('\n', "\\n")
+                .replace('\t', "\\t")
+                .replace('\r', "\\r")
+                .replace('"', "\\\"")
+        ),
+        // Whole floats are written with a fraction so they are read back as floats.
+        VariableTypes::FLOAT if !value.value.contains('.') => format!("{}.0", value.value),
+        VariableTypes::NULL => "null".to_string(),
+        _ => value.value.clone(),
+    }
+}
+
+fn format_expression(expression: &Expression, indent: usize) -> String {
+    match expression {
+        Expression::Literal { value, .. } => format_literal(value),
+        Expression::Variable { name, .. } => name.clone(),
+        Expression::Call {
+            namespace,
+            name,
+            args,
+            keywords,
+            ..
+        } => {
+            let name = match namespace {
+                Some(namespace) => format!("{}.{}", namespace, name),
+                None => name.clone(),
+            };
+            let mut args: Vec<String> = args
+                .iter()
+                .map(|arg| format_expression(arg, indent))
+                .collect();
+            args.extend(keywords.iter().map(|keyword| {
+                let value = format_expression(&keyword.value, indent);
+                format!("{}={}", keyword.name, value)
+            }));
+            format!("{}({})", name, args.join(", "))
+        }
+        Expression::List { items, .. } => {
+            let items: Vec<String> = items
+                .iter()
+                .map(|item| format_expression(item, indent))
+                .collect();
+            format!("[{}]", items.join(", "))
+        }
+        Expression::Struct { name, fields, .. } => {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|field| {
+                    let value = format_expression(&field.value, indent);
+                    format!("{}: {}", field.name, value)
+                })
+                .collect();
+            format_struct(name, &fields)
+        }
+        Expression::Variant {
+            enum_name,
+            variant,
+            args,
+            ..
+        } => {
+            let args: Vec<String> = args
+                .iter()
+                .map(|arg| format_expression(arg, indent))
+                .collect();
+            format_variant(enum_name, variant, &args)
+        }
+        Expression::Field { value, field, .. } => {
+            format!("{}.{}", format_expression(value, indent), field)
+        }
+        Expression::Function(decl) => {
+            let mut output = String::new();
+            format_function(decl, indent, &mut output);
+            output
+        }
+    }
+}
+
+fn format_pattern(pattern: &Pattern) -> String {
+    match pattern {
+        Pattern::Wildcard => "_".to_string(),
+        Pattern::Literal(value) => format_literal(value),
+        Pattern::Binding(name) => name.clone(),
+        Pattern::Variant {
+            enum_name,
+            variant,
+            items,
+            ..
+        } => {
+            let items: Vec<String> = items.iter().map(format_pattern).collect();
+            format_variant(enum_name, variant, &items)
+        }
+        Pattern::List { items, rest } => {
+            let mut items: Vec<String> = items.iter().map(format_pattern).collect();
+            if let Some(rest) = rest {
+                items.push(format!("...{}", rest));
+            }
+            format!("[{}]", items.join(", "))
+        }
+    }
+}
+
+fn format_function(decl: &FunctionDecl, indent: usize, output: &mut String) {
+    let mut params: Vec<String> = decl
+        .params
+        .iter()
+        .map(|param| {
+            let mut param_string = param.name.clone();
+            if let Some(annotation) = param.annotation {
+                param_string.push_str(&format!(": {}", format_annotation(annotation)));
+            }
+            if let Some(default) = &param.default {
+                param_string.push_str(&format!(" = {}", format_expression(default, indent)));
+            }
+            param_string
+        })
+        .collect();
+    if let Some(rest) = &decl.rest {
+        params.push(format!("...{}", rest));
+    }
+    output.push_str("fn");
+    if decl.name != ANONYMOUS_FUNCTION {
+        output.push_str(&format!(" {}", decl.name));
+    }
+    output.push_str(&format!("({}) ", params.join(", ")));
+    if let Some(return_type) = decl.return_type {
+        output.push_str(&format!("-> {} ", format_annotation(return_type)));
+    }
+    format_block(&decl.body, indent, output);
+}
@@ -0,0 +1,115 @@
+use std::convert::TryInto;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+
+use crate::buildin_functions::expect_args;
+use crate::buildin_functions::string_functions::string_argument;
+use crate::errors::{raise, ErrorKind};
+use crate::host::Host;
+use crate::limits::check_memory;
+use crate::variables::{VariableContent, VariableTypes};
+
+// Raises the error of the file system as a catchable io error.
+fn io_error(func_name: &str, path: &str, error: io::Error) -> ! {
+    let message = format!("Function {} failed for '{}': {}.", func_name, path, error);
+    raise(ErrorKind::Io, message)
+}
+
+// The functions for the files in the root directory of the host.
+pub fn file_read(args: Vec<VariableContent>, host: &mut Host) -> VariableContent {
+    expect_args(&args, 1, "read_file");
+    let path = string_argument(&args[0], "read_file");
+    let full = host.path(path);
+    // The file is only read when it fits, a file that grew since is checked again.
+    match fs::metadata(&full) {
+        Ok(metadata) => check_memory(metadata.len().try_into().unwrap_or(usize::MAX)),
+        Err(error) => io_error("read_file", path, error),
+    }
+    match fs::read_to_string(full) {
+        Ok(content) => {
+            check_memory(content.len());
+            VariableContent::new(content, VariableTypes::STRING)
+        }
+        Err(error) => io_error("read_file", path, error),
+    }
+}
+
+pub fn file_write(args: Vec<VariableContent>, host: &mut Host) -> VariableContent {
+    expect_args(&args, 2, "write_file");
+    let path = string_argument(&args[0], "write_file");
+    let content = string_argument(&args[1], "write_file");
+    if let Err(error) = fs::write(host.path(path), content) {
+        io_error("write_file", path, error);
+    }
+    VariableContent::null()
+}
+
+pub fn file_append(args: Vec<VariableContent>, host: &mut Host) -> VariableContent {
+    expect_args(&args, 2, "append_file");
+    let path = string_argument(&args[0], "append_file");
+    let content = string_argument(&args[1], "append_file");
+    let appended = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(host.path(path))
+        .and_then(|mut file| file.write_all(content.as_bytes()));
+    if let Err(error) = appended {
+        io_error("append_file", path, error);
+    }
+    VariableContent::null()
+}
+
+pub fn file_exists(args: Vec<VariableContent>, host: &mut Host) -> VariableContent {
+    expect_args(&args, 1, "exists");
+    let path = host.path(string_argument(&args[0], "exists"));
+    VariableContent::new(path.exists().to_string(), VariableTypes::BOOL)
+}
+
+// The names in the directory, sorted so every system gives the same list.
+pub fn file_list_dir(args: Vec<VariableContent>, host: &mut Host) -> VariableContent {
+    expect_args(&args, 1, "list_dir");
+    let path = string_argument(&args[0], "list_dir");
+    let entries = fs::read_dir(host.path(path)).and_then(|entries| {
+        entries
+            .map(|entry| entry.map(|entry| entry.file_name().to_string_lossy().to_string()))
+            .collect::<Result<Vec<String>, io::Error>>()
+    });
+    let mut names = entries.unwrap_or_else(|error| io_error("list_dir", path, error));
+    names.sort();
+    let names = names
+        .into_iter()
+        .map(|name| VariableContent::new(name, VariableTypes::STRING))
+        .collect();
+    VariableContent::list(names)
+}
+
+// Also makes the directories it is in.
+pub fn file_mkdir(args: Vec<VariableContent>, host: &mut Host) -> VariableContent {
+    expect_args(&args, 1, "mkdir");
+    let path = string_argument(&args[0], "mkdir");
+    if let Err(error) = fs::create_dir_all(host.path(path)) {
+        io_error("mkdir", path, error);
+    }
+    VariableContent::null()
+}
+
+// Removes a file or an empty directory, but never the root directory.
+pub fn file_remove(args: Vec<VariableContent>, host: &mut Host) -> VariableContent {
+    expect_args(&args, 1, "remove");
+    let path = string_argument(&args[0], "remove");
+    let target = host.path(path);
+    if host.path(".") == target {
+        raise(
+            ErrorKind::Permission,
+            "Can't remove the root directory.".to_string(),
+        );
+    }
+    let removed = match target.is_dir() {
+        true => fs::remove_dir(&target),
+        false => fs::remove_file(&target),
+    };
+    if let Err(error) = removed {
+        io_error("remove", path, error);
+    }
+    VariableContent::null()
+}
@@ -0,0 +1,297 @@
+use crate::buildin_functions::string_functions::string_argument;
+use crate::errors::{raise, ErrorKind};
+use crate::host::Host;
+use crate::limits::check_memory;
+use crate::variables::{format_struct, format_variant, VariableContent, VariableTypes};
+
+// Fills in the placeholders of the template like `{}`, `{1}`, `{name:>8.2}` or `{:?}`.
+pub fn simple_format(args: Vec<VariableContent>) -> VariableContent {
+    VariableContent::new(format_template(&args, "format"), VariableTypes::STRING)
+}
+
+// Like format, but prints the text without adding a newline.
+pub fn simple_printf(args: Vec<VariableContent>, host: &mut Host) -> VariableContent {
+    host.write(&format_template(&args, "printf"));
+    VariableContent::null()
+}
+
+fn format_template(args: &[VariableContent], func_name: &str) -> String {
+    if args.is_empty() {
+        let message = format!("Function {} needs a template.", func_name);
+        raise(ErrorKind::Argument, message);
+    }
+    let template = string_argument(&args[0], func_name);
+    let values = &args[1..];
+    let mut output = String::new();
+    let mut next = 0; // The value of the next `{}`.
+    let mut chars = template.chars();
+    while let Some(character) = chars.next() {
+        match character {
+            '{' if chars.as_str().starts_with('{') => {
+                chars.next();
+                output.push('{');
+            }
+            '{' => {
+                let rest = chars.as_str();
+                let end = rest
+                    .find('}')
+                    .unwrap_or_else(|| invalid(template, "a '{' isn't closed"));
+                let (argument, spec) = rest[..end].split_once(':').unwrap_or((&rest[..end], ""));
+                let value = find_value(values, argument, &mut next, template);
+                let spec = parse_spec(spec, template);
+                output.push_str(&format_value(value, &spec));
+                chars = rest[end + 1..].chars();
+            }
+            '}' if chars.as_str().starts_with('}') => {
+                chars.next();
+                output.push('}');
+            }
+            '}' => invalid(template, "a '}' isn't opened, write '}}' for one"),
+            _ => output.push(character),
+        }
+    }
+    output
+}
+
+fn invalid(template: &str, reason: &str) -> ! {
+    let message = format!("Invalid format \"{}\": {}.", template, reason);
+    raise(ErrorKind::Value, message)
+}
+
+// Nothing takes the next value, a number takes the value at that position and a name takes
+// the field of the first struct that has it.
+fn find_value<'a>(
+    values: &'a [VariableContent],
+    argument: &str,
+    next: &mut usize,
+    template: &str,
+) -> &'a VariableContent {
+    let argument = argument.trim();
+    let index = if argument.is_empty() {
+        *next += 1;
+        *next - 1
+    } else if let Ok(index) = argument.parse::<usize>() {
+        index
+    } else {
+        return values
+            .iter()
+            .filter_map(|value| value.as_struct())
+            .find_map(|(decl, fields)| {
+                let index = decl
+                    .fields
+                    .iter()
+                    .position(|field| field.name == argument)?;
+                Some(&fields[index])
+            })
+            .unwrap_or_else(|| {
+                let message = format!("Format has no struct with a field {}.", argument);
+                raise(ErrorKind::Argument, message)
+            });
+    };
+    values.get(index).unwrap_or_else(|| {
+        let message = format!(
+            "Format \"{}\" needs a value at position {} but got {} values.",
+            template,
+            index,
+            values.len()
+        );
+        raise(ErrorKind::Argument, message)
+    })
+}
+
+// The widest padding and the most digits after the point a spec can ask for.
+const MAX_WIDTH: usize = 65535;
+
+#[derive(Default)]
+struct Spec {
+    fill: Option<char>,
+    align: Option<char>, // One of '<', '^' and '>'.
+    plus: bool,
+    alternate: bool, // Adds 0x, 0o or 0b before the digits.
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+    kind: Option<char>, // One of '?', 'x', 'X', 'o' and 'b'.
+}
+
+// Like the ones of Rust: [[fill]align][+][#][0][width][.precision][type].
+fn parse_spec(spec: &str, template: &str) -> Spec {
+    let mut parsed = Spec::default();
+    let chars: Vec<char> = spec.chars().collect();
+    let mut position = 0;
+    let is_align = |character: Option<&char>| matches!(character, Some('<' | '^' | '>'));
+    if is_align(chars.get(1)) {
+        parsed.fill = Some(chars[0]);
+        parsed.align = Some(chars[1]);
+        position = 2;
+    } else if is_align(chars.first()) {
+        parsed.align = Some(chars[0]);
+        position = 1;
+    }
+    if chars.get(position) == Some(&'+') {
+        parsed.plus = true;
+        position += 1;
+    }
+    if chars.get(position) == Some(&'#') {
+        parsed.alternate = true;
+        position += 1;
+    }
+    if chars.get(position) == Some(&'0') {
+        parsed.zero = true;
+        position += 1;
+    }
+    let digits = |position: &mut usize, what: &str| {
+        let start = *position;
+        while chars.get(*position).is_some_and(char::is_ascii_digit) {
+            *position += 1;
+        }
+        if start == *position {
+            return None;
+        }
+        let number = chars[start..*position].iter().collect::<String>();
+        match number.parse() {
+            Ok(number) if number <= MAX_WIDTH => Some(number),
+            _ => invalid(
+                template,
+                &format!("a {} can be {} at most", what, MAX_WIDTH),
+            ),
+        }
+    };
+    parsed.width = digits(&mut position, "width").unwrap_or(0);
+    if chars.get(position) == Some(&'.') {
+        position += 1;
+        parsed.precision = digits(&mut position, "precision");
+        if parsed.precision.is_none() {
+            invalid(template, "a precision needs a number after the '.'");
+        }
+    }
+    if let Some(kind @ ('?' | 'x' | 'X' | 'o' | 'b')) = chars.get(position) {
+        parsed.kind = Some(*kind);
+        position += 1;
+    }
+    if position < chars.len() {
+        invalid(template, &format!("unknown format spec '{}'", spec));
+    }
+    parsed
+}
+
+fn format_value(value: &VariableContent, spec: &Spec) -> String {
+    let number = matches!(value.data_type, VariableTypes::INT | VariableTypes::FLOAT);
+    let mut text = match spec.kind {
+        Some('?') => debug(value),
+        Some(kind) => format_radix(value, kind, spec.alternate),
+        None => match spec.precision {
+            Some(precision) if number => {
+                check_memory(precision);
+                let float: f64 = value.value.parse().unwrap_or(0.0);
+                format!("{:.*}", precision, float)
+            }
+            Some(precision) => value.value.chars().take(precision).collect(),
+            None => value.value.clone(),
+        },
+    };
+    if spec.plus && number && !text.starts_with('-') {
+        text.insert(0, '+');
+    }
+
+    // Zeros go between the sign and the digits, other fill goes around the text.
+    let length = text.chars().count();
+    if length >= spec.width {
+        return text;
+    }
+    let padding = spec.width - length;
+    let fill_length = spec.fill.map_or(1, char::len_utf8);
+    check_memory(
+        text.len()
+            .saturating_add(padding.saturating_mul(fill_length)),
+    );
+    if spec.zero && number && spec.align.is_none() {
+        let sign = match text.starts_with(['+', '-']) {
+            true => text.remove(0).to_string(),
+            false => String::new(),
+        };
+        let radix = matches!(spec.kind, Some('x' | 'X' | 'o' | 'b'));
+        let prefix_length = match spec.alternate && radix {
+            true => 2,
+            false => 0,
+        };
+        let (prefix, digits) = text.split_at(prefix_length);
+        return format!("{}{}{}{}", sign, prefix, "0".repeat(padding), digits);
+    }
+    let fill = spec.fill.unwrap_or(' ').to_string();
+    let align = spec.align.unwrap_or(if number { '>' } else { '<' });
+    let (before, after) = match align {
+        '<' => (0, padding),
+        '^' => (padding / 2, padding - padding / 2),
+        _ => (padding, 0),
+    };
+    format!("{}{}{}", fill.repeat(before), text, fill.repeat(after))
+}
+
+// Like print shows the value, but strings are quoted with their quotes, backslashes and
+// control characters escaped, also inside of collections.
+fn debug(value: &VariableContent) -> String {
+    let shown = |values: &[VariableContent]| values.iter().map(debug).collect::<Vec<_>>();
+    if let Some(items) = value.as_list() {
+        return format!("[{}]", shown(items).join(", "));
+    }
+    if let Some((decl, fields)) = value.as_struct() {
+        let fields: Vec<String> = decl
+            .fields
+            .iter()
+            .zip(shown(fields))
+            .map(|(field, value)| format!("{}: {}", field.name, value))
+            .collect();
+        return format_struct(&decl.name, &fields);
+    }
+    if let Some((decl, variant, payload)) = value.as_variant() {
+        return format_variant(&decl.name, &decl.variants[variant].name, &shown(payload));
+    }
+    if value.data_type != VariableTypes::STRING {
+        return value.shown_inside();
+    }
+    let mut text = String::from("\"");
+    for character in value.value.chars() {
+        match character {
+            '"' => text.push_str("\\\""),
+            '\\' => text.push_str("\\\\"),
+            '\n' => text.push_str("\\n"),
+            '\t' => text.push_str("\\t"),
+            '\r' => text.push_str("\\r"),
+            _ if character.is_control() => text.push_str(&format!("\\u{{{:x}}}", character as u32)),
+            _ => text.push(character),
+        }
+    }
+    text.push('"');
+    text
+}
+
+// Negative numbers keep their sign before the digits.
+fn format_radix(value: &VariableContent, kind: char, alternate: bool) -> String {
+    let number: i64 = match value.data_type {
+        VariableTypes::INT => value.value.parse().unwrap_or_else(|_| {
+            let message = format!(
+                "Format type '{}' got an INT out of range: {}.",
+                kind, value.value
+            );
+            raise(ErrorKind::Value, message)
+        }),
+        _ => raise(
+            ErrorKind::Type,
+            format!(
+                "Format type '{}' expects an INT, got {:?}.",
+                kind, value.data_type
+            ),
+        ),
+    };
+    let magnitude = number.unsigned_abs();
+    let (digits, prefix) = match kind {
+        'x' => (format!("{:x}", magnitude), "0x"),
+        'X' => (format!("{:X}", magnitude), "0x"),
+        'o' => (format!("{:o}", magnitude), "0o"),
+        _ => (format!("{:b}", magnitude), "0b"),
+    };
+    let sign = if number < 0 { "-" } else { "" };
+    let prefix = if alternate { prefix } else { "" };
+    format!("{}{}{}", sign, prefix, digits)
+}
@@ -0,0 +1,620 @@
+use std::rc::Rc;
+
+use crate::ast::{Span, StructDecl, StructField};
+use crate::buildin_functions::expect_args;
+use crate::buildin_functions::string_functions::string_argument;
+use crate::errors::{raise, ErrorKind};
+use crate::limits::check_memory;
+use crate::variables::{VariableContent, VariableTypes};
+
+// The functions of the json module. Objects become structs named Object with their keys as
+// fields, in the order of the keys so the same object always looks the same.
+pub fn json_parse(args: Vec<VariableContent>) -> VariableContent {
+    expect_args(&args, 1, "json.parse");
+    let text = string_argument(&args[0], "json.parse");
+    let mut parser = JsonParser {
+        chars: text.chars().collect(),
+        position: 0,
+        line: 1,
+        column: 1,
+        depth: 0,
+    };
+    parser.skip_whitespace();
+    let value = parser.parse_value();
+    parser.skip_whitespace();
+    if parser.peek().is_some() {
+        parser.error("expected the end of the input");
+    }
+    value
+}
+
+// The most spaces a level is indented by, bigger indents are cut to it.
+const MAX_INDENT: i64 = 10;
+
+// Without an indent, or with 0, everything is on one line.
+pub fn json_stringify(args: Vec<VariableContent>) -> VariableContent {
+    let indent = match args.len() {
+        1 => 0,
+        2 if args[1].data_type == VariableTypes::INT => match args[1].value.parse::<i64>() {
+            Ok(indent) if indent >= 0 => indent.min(MAX_INDENT) as usize,
+            _ => {
+                let message = format!(
+                    "Function json.stringify can't indent by {} spaces.",
+                    args[1].value
+                );
+                raise(ErrorKind::Argument, message)
+            }
+        },
+        2 => raise(
+            ErrorKind::Type,
+            format!(
+                "Function json.stringify expects an INT indent, got {:?}.",
+                args[1].data_type
+            ),
+        ),
+        count => {
+            let message = format!(
+                "Function json.stringify takes at most 2 arguments but got {}.",
+                count
+            );
+            raise(ErrorKind::Argument, message)
+        }
+    };
+    let mut json = String::new();
+    write_value(&mut json, &args[0], indent, 0);
+    VariableContent::new(json, VariableTypes::STRING)
+}
+
+// The most arrays and objects inside of each other.
+const MAX_DEPTH: usize = 128;
+
+struct JsonParser {
+    chars: Vec<char>,
+    position: usize,
+    line: usize,
+    column: usize,
+    depth: usize, // The arrays and objects the parser is in.
+}
+
+impl JsonParser {
+    // Errors point at the character of the input where they were found.
+    fn error(&self, message: &str) -> ! {
+        let message = format!(
+            "Invalid JSON at line {}, column {}: {}.",
+            self.line, self.column, message
+        );
+        raise(ErrorKind::Value, message)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let next = self.peek()?;
+        self.position += 1;
+        match next {
+            '\n' => {
+                self.line += 1;
+                self.column = 1;
+            }
+            _ => self.column += 1,
+        }
+        Some(next)
+    }
+
+    fn expect(&mut self, expected: char) {
+        match self.peek() {
+            Some(next) if next == expected => {
+                self.next();
+            }
+            _ => self.error(&format!("expected '{}'", expected)),
+        }
+    }
+
+    fn enter(&mut self) {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            self.error(&format!("nested deeper than {} levels", MAX_DEPTH));
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.next();
+        }
+    }
+
+    fn parse_value(&mut self) -> VariableContent {
+        match self.peek() {
+            Some('{') => self.parse_object(),
+            Some('[') => self.parse_array(),
+            Some('"') => VariableContent::new(self.parse_string(), VariableTypes::STRING),
+            Some('-' | '0'..='9') => self.parse_number(),
+            Some('t') => self.parse_word("true", VariableTypes::BOOL),
+            Some('f') => self.parse_word("false", VariableTypes::BOOL),
+            Some('n') => self.parse_word("null", VariableTypes::NULL),
+            Some(_) => self.error("expected a value"),
+            None => self.error("unexpected end of the input"),
+        }
+    }
+
+    fn parse_word(&mut self, word: &str, data_type: VariableTypes) -> VariableContent {
+        for expected in word.chars() {
+            if self.peek() != Some(expected) {
+                self.error(&format!("expected '{}'", word));
+            }
+            self.next();
+        }
+        match data_type {
+            VariableTypes::NULL => VariableContent::null(),
+            _ => VariableContent::new(word.to_string(), data_type),
+        }
+    }
+
+    // Numbers with a fraction or an exponent are FLOATs, the others INTs.
+    fn parse_number(&mut self) -> VariableContent {
+        let start = self.position;
+        if self.peek() == Some('-') {
+            self.next();
+        }
+        match self.peek() {
+            Some('0') => {
+                self.next();
+            }
+            Some('1'..='9') => self.skip_digits(),
+            _ => self.error("expected a digit"),
+        }
+        let mut float = false;
+        if self.peek() == Some('.') {
+            float = true;
+            self.next();
+            self.expect_digits();
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            float = true;
+            self.next();
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.next();
+            }
+            self.expect_digits();
+        }
+
+        let text: String = self.chars[start..self.position].iter().collect();
+        match text.parse::<i64>() {
+            Ok(int) if !float => VariableContent::new(int.to_string(), VariableTypes::INT),
+            _ => {
+                let number: f64 = text.parse().unwrap();
+                if !number.is_finite() {
+                    self.error("the number is too big for a FLOAT");
+                }
+                VariableContent::new(number.to_string(), VariableTypes::FLOAT)
+            }
+        }
+    }
+
+    fn skip_digits(&mut self) {
+        while matches!(self.peek(), Some('0'..='9')) {
+            self.next();
+        }
+    }
+
+    fn expect_digits(&mut self) {
+        if !matches!(self.peek(), Some('0'..='9')) {
+            self.error("expected a digit");
+        }
+        self.skip_digits();
+    }
+
+    fn parse_string(&mut self) -> String {
+        self.expect('"');
+        let mut text = String::new();
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    self.next();
+                    return text;
+                }
+                Some('\\') => {
+                    self.next();
+                    text.push(self.parse_escape());
+                }
+                Some(next) if (next as u32) < 0x20 => {
+                    self.error("control characters in strings have to be escaped")
+                }
+                Some(next) => {
+                    self.next();
+                    text.push(next);
+                }
+                None => self.error("unterminated string"),
+            }
+        }
+    }
+
+    fn parse_escape(&mut self) -> char {
+        let escaped = match self.peek() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('/') => '/',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('u') => {
+                self.next();
+                return self.parse_unicode();
+            }
+            _ => self.error("invalid escape"),
+        };
+        self.next();
+        escaped
+    }
+
+    // A character outside of the basic plane is written as two escapes of a surrogate pair.
+    fn parse_unicode(&mut self) -> char {
+        let first = self.parse_hex();
+        let code = match first {
+            0xD800..=0xDBFF => {
+                if self.peek() != Some('\\') {
+                    self.error("expected the second half of a surrogate pair");
+                }
+                self.next();
+                if self.peek() != Some('u') {
+                    self.error("expected the second half of a surrogate pair");
+                }
+                self.next();
+                let second = self.parse_hex();
+                if !(0xDC00..=0xDFFF).contains(&second) {
+                    self.error("invalid second half of a surrogate pair");
+                }
+                0x10000 + ((first - 0xD800) << 10) + (second - 0xDC00)
+            }
+            _ => first,
+        };
+        char::from_u32(code).unwrap_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn parse_hex(&mut self) -> u32 {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self
+                .peek()
+                .and_then(|next| next.to_digit(16))
+                .unwrap_or_else(|| self.error("expected 4 hex digits"));
+            self.next();
+            code = code * 16 + digit;
+        }
+        code
+    }
+
+    fn parse_array(&mut self) -> VariableContent {
+        self.expect('[');
+        self.enter();
+        self.skip_whitespace();
+        let mut items = Vec::new();
+        if self.peek() == Some(']') {
+            self.next();
+            self.depth -= 1;
+            return VariableContent::list(items);
+        }
+        loop {
+            self.skip_whitespace();
+            items.push(self.parse_value());
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.next();
+                }
+                Some(']') => {
+                    self.next();
+                    self.depth -= 1;
+                    return VariableContent::list(items);
+                }
+                _ => self.error("expected ',' or ']'"),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> VariableContent {
+        self.expect('{');
+        self.enter();
+        self.skip_whitespace();
+        let mut entries: Vec<(String, VariableContent)> = Vec::new();
+        if self.peek() == Some('}') {
+            self.next();
+            self.depth -= 1;
+            return object(entries);
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                self.error("expected a key");
+            }
+            let key = self.parse_string();
+            self.skip_whitespace();
+            self.expect(':');
+            self.skip_whitespace();
+            let value = self.parse_value();
+            entries.push((key, value));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.next();
+                }
+                Some('}') => {
+                    self.next();
+                    self.depth -= 1;
+                    return object(entries);
+                }
+                _ => self.error("expected ',' or '}'"),
+            }
+        }
+    }
+}
+
+// A key given twice keeps the last value, the sort is stable so that one comes last.
+fn object(mut entries: Vec<(String, VariableContent)>) -> VariableContent {
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut unique: Vec<(String, VariableContent)> = Vec::with_capacity(entries.len());
+    for entry in entries {
+        match unique.last_mut() {
+            Some(last) if last.0 == entry.0 => *last = entry,
+            _ => unique.push(entry),
+        }
+    }
+    let entries = unique;
+    let fields = entries
+        .iter()
+        .map(|(name, _)| StructField {
+            name: name.clone(),
+            annotation: None,
+        })
+        .collect();
+    let decl = Rc::new(StructDecl {
+        name: "Object".to_string(),
+        fields,
+        span: Span::default(),
+    });
+    let values = entries.into_iter().map(|(_, value)| value).collect();
+    VariableContent::record(decl, values)
+}
+
+// The keys of structs are sorted, so the same value always gives the same JSON.
+fn write_value(json: &mut String, value: &VariableContent, indent: usize, depth: usize) {
+    match value.data_type {
+        VariableTypes::INT | VariableTypes::BOOL => json.push_str(&value.value),
+        VariableTypes::NULL => json.push_str("null"),
+        VariableTypes::FLOAT => {
+            let number: f64 = value.value.parse().unwrap_or(f64::NAN);
+            if !number.is_finite() {
+                let message = format!("JSON has no number like {}.", value.value);
+                raise(ErrorKind::Value, message);
+            }
+            json.push_str(&value.value);
+            // Whole floats get a fraction so they are read back as floats.
+            if !value.value.contains(['.', 'e', 'E']) {
+                json.push_str(".0");
+            }
+        }
+        VariableTypes::STRING => write_string(json, &value.value),
+        VariableTypes::LIST => {
+            let items = value.as_list().unwrap();
+            let items: Vec<(Option<&str>, &VariableContent)> =
+                items.iter().map(|item| (None, item)).collect();
+            write_collection(json, ('[', ']'), &items, indent, depth);
+        }
+        VariableTypes::STRUCT => {
+            let (decl, fields) = value.as_struct().unwrap();
+            let mut entries: Vec<(Option<&str>, &VariableContent)> = decl
+                .fields
+                .iter()
+                .zip(fields)
+                .map(|(field, value)| (Some(field.name.as_str()), value))
+                .collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            write_collection(json, ('{', '}'), &entries, indent, depth);
+        }
+        _ => raise(
+            ErrorKind::Type,
+            format!(
+                "Function json.stringify can't turn a {:?} into JSON.",
+                value.data_type
+            ),
+        ),
+    }
+}
+
+// Writes the items of an array, or the entries of an object when they have keys.
+fn write_collection(
+    json: &mut String,
+    (open, close): (char, char),
+    entries: &[(Option<&str>, &VariableContent)],
+    indent: usize,
+    depth: usize,
+) {
+    json.push(open);
+    for (index, (key, value)) in entries.iter().enumerate() {
+        if index > 0 {
+            json.push(',');
+        }
+        if indent > 0 {
+            json.push('\n');
+            indent_line(json, indent * (depth + 1));
+        }
+        if let Some(key) = key {
+            write_string(json, key);
+            json.push(':');
+            if indent > 0 {
+                json.push(' ');
+            }
+        }
+        write_value(json, value, indent, depth + 1);
+    }
+    if indent > 0 && !entries.is_empty() {
+        json.push('\n');
+        indent_line(json, indent * depth);
+    }
+    json.push(close);
+}
+
+// The JSON written so far has to fit in the memory with the spaces.
+fn indent_line(json: &mut String, spaces: usize) {
+    check_memory(json.len().saturating_add(spaces));
+    json.push_str(&" ".repeat(spaces));
+}
+
+fn write_string(json: &mut String, text: &str) {
+    json.push('"');
+    for character in text.chars() {
+        match character {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            '\u{8}' => json.push_str("\\b"),
+            '\u{c}' => json.push_str("\\f"),
+            character if (character as u32) < 0x20 => {
+                json.push_str(&format!("\\u{:04x}", character as u32))
+            }
+            character => json.push(character),
+        }
+    }
+    json.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::catch_error;
+
+    fn string(text: &str) -> VariableContent {
+        VariableContent::new(text.to_string(), VariableTypes::STRING)
+    }
+
+    fn int(number: i64) -> VariableContent {
+        VariableContent::new(number.to_string(), VariableTypes::INT)
+    }
+
+    fn parse(json: &str) -> VariableContent {
+        json_parse(vec![string(json)])
+    }
+
+    fn stringify(args: Vec<VariableContent>) -> String {
+        json_stringify(args).value
+    }
+
+    fn error(function: impl FnOnce() -> VariableContent) -> (ErrorKind, String) {
+        let error = catch_error(function).unwrap_err();
+        (error.kind, error.message)
+    }
+
+    #[test]
+    fn escapes_are_read_and_written() {
+        let text = parse(r#""q\"b\\s\/n\nt\tb\bf\fu\u0041""#);
+        assert_eq!(text.value, "q\"b\\s/n\nt\tb\u{8}f\u{c}uA");
+        assert_eq!(stringify(vec![text]), r#""q\"b\\s/n\nt\tb\bf\fuA""#);
+        assert_eq!(stringify(vec![string("\u{1}\u{1f}")]), r#""\u0001\u001f""#);
+    }
+
+    #[test]
+    fn surrogate_pairs_make_one_character() {
+        assert_eq!(parse(r#""\ud83d\ude00""#).value, "\u{1F600}");
+        assert_eq!(parse(r#""\u00e9""#).value, "é");
+        let (kind, message) = error(|| parse(r#""\ud83d""#));
+        assert_eq!(kind, ErrorKind::Value);
+        assert_eq!(
+            message,
+            "Invalid JSON at line 1, column 8: expected the second half of a surrogate pair."
+        );
+        let (_, message) = error(|| parse(r#""\ud83d\u0041""#));
+        assert!(message.ends_with("invalid second half of a surrogate pair."));
+    }
+
+    #[test]
+    fn errors_point_at_the_line_and_column() {
+        let (_, message) = error(|| parse("[1,\n  2,\n  x]"));
+        assert_eq!(
+            message,
+            "Invalid JSON at line 3, column 3: expected a value."
+        );
+        let (_, message) = error(|| parse("{\"a\": 1} 2"));
+        assert_eq!(
+            message,
+            "Invalid JSON at line 1, column 10: expected the end of the input."
+        );
+    }
+
+    #[test]
+    fn ints_and_floats_stay_apart() {
+        assert_eq!(parse("12").data_type, VariableTypes::INT);
+        assert_eq!(parse("-0").data_type, VariableTypes::INT);
+        assert_eq!(parse("12.0").data_type, VariableTypes::FLOAT);
+        assert_eq!(parse("1e2").data_type, VariableTypes::FLOAT);
+        // INTs too big for 64 bits are read as FLOATs.
+        assert_eq!(parse("9223372036854775808").data_type, VariableTypes::FLOAT);
+        assert_eq!(stringify(vec![parse("12.0")]), "12.0");
+        assert_eq!(stringify(vec![parse("12")]), "12");
+        assert_eq!(stringify(vec![parse("[1, 2.5, 1e2]")]), "[1,2.5,100.0]");
+    }
+
+    #[test]
+    fn numbers_too_big_for_a_float_raise() {
+        assert_eq!(parse("1e308").data_type, VariableTypes::FLOAT);
+        let (kind, message) = error(|| parse("[1e400]"));
+        assert_eq!(kind, ErrorKind::Value);
+        assert_eq!(
+            message,
+            "Invalid JSON at line 1, column 7: the number is too big for a FLOAT."
+        );
+        let (_, message) = error(|| parse("-1e400"));
+        assert!(message.ends_with("the number is too big for a FLOAT."));
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let nested = |depth| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert_eq!(
+            stringify(vec![parse(&nested(MAX_DEPTH))]).len(),
+            2 * MAX_DEPTH
+        );
+        let (kind, message) = error(|| parse(&nested(MAX_DEPTH + 1)));
+        assert_eq!(kind, ErrorKind::Value);
+        assert_eq!(
+            message,
+            "Invalid JSON at line 1, column 130: nested deeper than 128 levels."
+        );
+        let objects = "{\"a\": ".repeat(MAX_DEPTH + 1);
+        let (_, message) = error(|| parse(&objects));
+        assert!(message.ends_with("nested deeper than 128 levels."));
+    }
+
+    #[test]
+    fn keys_given_twice_keep_the_last_value() {
+        let object = parse(r#"{"b": 1, "a": 2, "b": 3, "a": 4, "c": 5, "b": 6}"#);
+        assert_eq!(stringify(vec![object]), r#"{"a":4,"b":6,"c":5}"#);
+        let keys: Vec<String> = (0..20000)
+            .map(|i| format!("\"{}\": {}", i % 10000, i))
+            .collect();
+        let object = parse(&format!("{{{}}}", keys.join(",")));
+        let json = stringify(vec![object]);
+        assert!(json.starts_with(r#"{"0":10000,"1":10001,"#));
+    }
+
+    #[test]
+    fn indents_are_checked_and_capped() {
+        let list = parse("[1]");
+        assert_eq!(stringify(vec![list.clone(), int(2)]), "[\n  1\n]");
+        assert_eq!(
+            stringify(vec![list.clone(), int(1000)]),
+            stringify(vec![list.clone(), int(10)])
+        );
+        let (kind, message) = error(|| json_stringify(vec![list, int(-1)]));
+        assert_eq!(kind, ErrorKind::Argument);
+        assert_eq!(
+            message,
+            "Function json.stringify can't indent by -1 spaces."
+        );
+    }
+}
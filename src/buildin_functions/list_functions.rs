@@ -0,0 +1,142 @@
+use std::cmp::Ordering;
+
+use crate::buildin_functions::list_argument;
+use crate::errors::{raise, ErrorKind};
+use crate::functions::FunctionCaller;
+use crate::variables::{VariableContent, VariableTypes};
+
+fn bool_result(value: &VariableContent, func_name: &str) -> bool {
+    match value.data_type {
+        VariableTypes::BOOL => value.value == "true",
+        _ => raise(
+            ErrorKind::Type,
+            format!(
+                "Function {} expects a BOOL, got {:?}.",
+                func_name, value.data_type
+            ),
+        ),
+    }
+}
+
+fn int(value: usize) -> VariableContent {
+    VariableContent::new(value.to_string(), VariableTypes::INT)
+}
+
+// Orders numbers, strings and bools, the comparator of sort is used for anything else.
+fn compare_values(a: &VariableContent, b: &VariableContent) -> Ordering {
+    let numbers = [VariableTypes::INT, VariableTypes::FLOAT];
+    if numbers.contains(&a.data_type) && numbers.contains(&b.data_type) {
+        let a = a.value.parse::<f64>().unwrap();
+        let b = b.value.parse::<f64>().unwrap();
+        return a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+    }
+    match (a.data_type, b.data_type) {
+        (VariableTypes::STRING, VariableTypes::STRING)
+        | (VariableTypes::BOOL, VariableTypes::BOOL) => a.value.cmp(&b.value),
+        _ => raise(
+            ErrorKind::Type,
+            format!("Can't compare {:?} with {:?}.", a.data_type, b.data_type),
+        ),
+    }
+}
+
+// List functions, they call the function given as their second argument.
+pub fn simple_map(args: Vec<VariableContent>, call: &mut FunctionCaller) -> VariableContent {
+    let items = list_argument(&args[0], "map");
+    let mapped = items
+        .iter()
+        .cloned()
+        .map(|item| call(&args[1], vec![item]))
+        .collect();
+    VariableContent::list(mapped)
+}
+
+pub fn simple_filter(args: Vec<VariableContent>, call: &mut FunctionCaller) -> VariableContent {
+    let items = list_argument(&args[0], "filter");
+    let kept = items
+        .iter()
+        .filter(|item| bool_result(&call(&args[1], vec![(*item).clone()]), "filter"))
+        .cloned()
+        .collect();
+    VariableContent::list(kept)
+}
+
+// Without an initial value it starts with the first item.
+pub fn simple_reduce(args: Vec<VariableContent>, call: &mut FunctionCaller) -> VariableContent {
+    let mut items = list_argument(&args[0], "reduce").iter().cloned();
+    let initial = match args.get(2) {
+        Some(initial) => initial.clone(),
+        None => items.next().unwrap_or_else(|| {
+            let message = "Can't reduce an empty list without an initial value.".to_string();
+            raise(ErrorKind::Value, message)
+        }),
+    };
+    items.fold(initial, |result, item| call(&args[1], vec![result, item]))
+}
+
+// Without a function the items themselves have to be bools.
+pub fn simple_any(args: Vec<VariableContent>, call: &mut FunctionCaller) -> VariableContent {
+    let mut items = list_argument(&args[0], "any").iter().cloned();
+    let found = items.any(|item| match args.get(1) {
+        Some(function) => bool_result(&call(function, vec![item]), "any"),
+        None => bool_result(&item, "any"),
+    });
+    VariableContent::new(found.to_string(), VariableTypes::BOOL)
+}
+
+pub fn simple_all(args: Vec<VariableContent>, call: &mut FunctionCaller) -> VariableContent {
+    let mut items = list_argument(&args[0], "all").iter().cloned();
+    let every = items.all(|item| match args.get(1) {
+        Some(function) => bool_result(&call(function, vec![item]), "all"),
+        None => bool_result(&item, "all"),
+    });
+    VariableContent::new(every.to_string(), VariableTypes::BOOL)
+}
+
+// The comparator returns a number, below zero when its first argument goes first.
+pub fn simple_sort(args: Vec<VariableContent>, call: &mut FunctionCaller) -> VariableContent {
+    let mut items = list_argument(&args[0], "sort").clone();
+    match args.get(1) {
+        Some(comparator) => items.sort_by(|a, b| {
+            let result = call(comparator, vec![a.clone(), b.clone()]);
+            match result.data_type {
+                VariableTypes::INT | VariableTypes::FLOAT => {
+                    let order = result.value.parse::<f64>().unwrap();
+                    order.partial_cmp(&0.0).unwrap_or(Ordering::Equal)
+                }
+                _ => raise(
+                    ErrorKind::Type,
+                    format!(
+                        "The comparator of sort should return a number, got {:?}.",
+                        result.data_type
+                    ),
+                ),
+            }
+        }),
+        None => items.sort_by(compare_values),
+    }
+    VariableContent::list(items)
+}
+
+// Pairs every item with its index.
+pub fn simple_enumerate(args: Vec<VariableContent>, _: &mut FunctionCaller) -> VariableContent {
+    let items = list_argument(&args[0], "enumerate");
+    let pairs = items
+        .iter()
+        .cloned()
+        .enumerate()
+        .map(|(i, item)| VariableContent::list(vec![int(i), item]))
+        .collect();
+    VariableContent::list(pairs)
+}
+
+// Groups the items at the same index, it stops at the end of the shortest list.
+pub fn simple_zip(args: Vec<VariableContent>, _: &mut FunctionCaller) -> VariableContent {
+    let lists: Vec<&Vec<VariableContent>> =
+        args.iter().map(|arg| list_argument(arg, "zip")).collect();
+    let length = lists.iter().map(|list| list.len()).min().unwrap_or(0);
+    let groups = (0..length)
+        .map(|i| VariableContent::list(lists.iter().map(|list| list[i].clone()).collect()))
+        .collect();
+    VariableContent::list(groups)
+}
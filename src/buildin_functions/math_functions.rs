@@ -1,29 +1,31 @@
+use std::cmp::Ordering;
+
+use crate::buildin_functions::{expect_args, int_argument};
+use crate::errors::{raise, ErrorKind};
 use crate::variables::{VariableContent, VariableTypes};
 
 // Math functions.
 pub fn simple_add(args: Vec<VariableContent>) -> VariableContent {
     if args.len() < 2 {
-        panic!(
+        let message = format!(
             "Length of args was {}; there should atleast be 2 arguments.",
             args.len()
         );
+        raise(ErrorKind::Argument, message);
     }
 
     // Check if all types are supported.
     {
-        let mut tmp_args: Vec<VariableContent> = vec![
-            VariableContent {
-                value: "".to_string(),
-                data_type: VariableTypes::NULL
-            };
-            args.len()
-        ];
+        let mut tmp_args: Vec<VariableContent> = vec![VariableContent::null(); args.len()];
         tmp_args.clone_from_slice(&args[..]);
         for arg in tmp_args {
             match arg.data_type {
                 VariableTypes::FLOAT => {}
                 VariableTypes::INT => {}
-                _ => panic!("Argument can't be of type '{:?}'.", arg.data_type),
+                _ => raise(
+                    ErrorKind::Type,
+                    format!("Argument can't be of type '{:?}'.", arg.data_type),
+                ),
             }
         }
     }
@@ -36,35 +38,30 @@ pub fn simple_add(args: Vec<VariableContent>) -> VariableContent {
         sum += arg.value.parse::<f64>().unwrap();
     }
 
-    VariableContent {
-        value: sum.to_string(),
-        data_type: VariableTypes::FLOAT,
-    }
+    VariableContent::new(sum.to_string(), VariableTypes::FLOAT)
 }
 
 pub fn simple_sub(args: Vec<VariableContent>) -> VariableContent {
     if args.len() < 2 {
-        panic!(
+        let message = format!(
             "Length of args was {}; there should atleast be 2 arguments.",
             args.len()
         );
+        raise(ErrorKind::Argument, message);
     }
 
     // Check if all types are supported.
     {
-        let mut tmp_args: Vec<VariableContent> = vec![
-            VariableContent {
-                value: "".to_string(),
-                data_type: VariableTypes::NULL
-            };
-            args.len()
-        ];
+        let mut tmp_args: Vec<VariableContent> = vec![VariableContent::null(); args.len()];
         tmp_args.clone_from_slice(&args[..]);
         for arg in tmp_args {
             match arg.data_type {
                 VariableTypes::FLOAT => {}
                 VariableTypes::INT => {}
-                _ => panic!("Argument can't be of type '{:?}'.", arg.data_type),
+                _ => raise(
+                    ErrorKind::Type,
+                    format!("Argument can't be of type '{:?}'.", arg.data_type),
+                ),
             }
         }
     }
@@ -77,8 +74,130 @@ pub fn simple_sub(args: Vec<VariableContent>) -> VariableContent {
         sum -= arg.value.parse::<f64>().unwrap();
     }
 
-    VariableContent {
-        value: sum.to_string(),
-        data_type: VariableTypes::FLOAT,
+    VariableContent::new(sum.to_string(), VariableTypes::FLOAT)
+}
+
+// Gets the number of an argument that has to be an INT or a FLOAT.
+fn number_argument(arg: &VariableContent, func_name: &str) -> f64 {
+    match arg.data_type {
+        VariableTypes::INT | VariableTypes::FLOAT => arg.value.parse::<f64>().unwrap(),
+        _ => raise(
+            ErrorKind::Type,
+            format!(
+                "Function {} expects an INT or a FLOAT, got {:?}.",
+                func_name, arg.data_type
+            ),
+        ),
+    }
+}
+
+fn float(value: f64) -> VariableContent {
+    VariableContent::new(value.to_string(), VariableTypes::FLOAT)
+}
+
+fn int(value: i64) -> VariableContent {
+    VariableContent::new(value.to_string(), VariableTypes::INT)
+}
+
+// A whole FLOAT as an INT, when it fits in one.
+fn whole(value: f64, func_name: &str) -> VariableContent {
+    let limit = -(i64::MIN as f64);
+    if !(-limit..limit).contains(&value) {
+        let message = format!("Function {} can't make an INT of {}.", func_name, value);
+        raise(ErrorKind::Value, message);
+    }
+    int(value as i64)
+}
+
+// The functions of the math module.
+pub fn math_sqrt(args: Vec<VariableContent>) -> VariableContent {
+    expect_args(&args, 1, "math.sqrt");
+    let number = number_argument(&args[0], "math.sqrt");
+    if number < 0.0 {
+        let message = "Can't take the square root of a negative number.".to_string();
+        raise(ErrorKind::Value, message);
+    }
+    float(number.sqrt())
+}
+
+pub fn math_pow(args: Vec<VariableContent>) -> VariableContent {
+    expect_args(&args, 2, "math.pow");
+    let base = number_argument(&args[0], "math.pow");
+    let exponent = number_argument(&args[1], "math.pow");
+    float(base.powf(exponent))
+}
+
+// Keeps the type of the number.
+pub fn math_abs(args: Vec<VariableContent>) -> VariableContent {
+    expect_args(&args, 1, "math.abs");
+    match args[0].data_type {
+        VariableTypes::INT => {
+            let number = int_argument(&args[0], "math.abs");
+            int(number.checked_abs().unwrap_or_else(|| {
+                let message = format!(
+                    "Function math.abs can't make an INT of the absolute value of {}.",
+                    number
+                );
+                raise(ErrorKind::Value, message)
+            }))
+        }
+        _ => float(number_argument(&args[0], "math.abs").abs()),
+    }
+}
+
+// Floor, ceil and round give an INT.
+pub fn math_floor(args: Vec<VariableContent>) -> VariableContent {
+    expect_args(&args, 1, "math.floor");
+    whole(
+        number_argument(&args[0], "math.floor").floor(),
+        "math.floor",
+    )
+}
+
+pub fn math_ceil(args: Vec<VariableContent>) -> VariableContent {
+    expect_args(&args, 1, "math.ceil");
+    whole(number_argument(&args[0], "math.ceil").ceil(), "math.ceil")
+}
+
+pub fn math_round(args: Vec<VariableContent>) -> VariableContent {
+    expect_args(&args, 1, "math.round");
+    whole(
+        number_argument(&args[0], "math.round").round(),
+        "math.round",
+    )
+}
+
+// Min and max give back the argument itself, so its type is kept. Two INTs are compared as
+// INTs.
+pub fn math_min(args: Vec<VariableContent>) -> VariableContent {
+    extreme(args, "math.min", Ordering::Less)
+}
+
+pub fn math_max(args: Vec<VariableContent>) -> VariableContent {
+    extreme(args, "math.max", Ordering::Greater)
+}
+
+fn extreme(args: Vec<VariableContent>, func_name: &str, better: Ordering) -> VariableContent {
+    let mut best: Option<VariableContent> = None;
+    for arg in args {
+        let number = number_argument(&arg, func_name);
+        let replaces = match &best {
+            None => true,
+            Some(best)
+                if arg.data_type == VariableTypes::INT && best.data_type == arg.data_type =>
+            {
+                int_argument(&arg, func_name).cmp(&int_argument(best, func_name)) == better
+            }
+            Some(best) => number.partial_cmp(&number_argument(best, func_name)) == Some(better),
+        };
+        if replaces {
+            best = Some(arg);
+        }
     }
+    best.unwrap_or_else(|| {
+        raise(
+            ErrorKind::Argument,
+            format!("Function {} needs at least 1 argument.", func_name),
+        )
+    })
 }
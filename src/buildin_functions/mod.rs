@@ -1 +1,58 @@
+pub mod file_functions;
+pub mod format_functions;
+pub mod json_functions;
+pub mod list_functions;
 pub mod math_functions;
+pub mod random_functions;
+pub mod string_functions;
+pub mod system_functions;
+pub mod time_functions;
+
+use crate::errors::{raise, ErrorKind};
+use crate::variables::{VariableContent, VariableTypes};
+
+// Panics unless the builtin got exactly that many arguments.
+pub fn expect_args(args: &[VariableContent], count: usize, func_name: &str) {
+    if args.len() != count {
+        let message = format!(
+            "Function {} takes {} arguments but got {}.",
+            func_name,
+            count,
+            args.len()
+        );
+        raise(ErrorKind::Argument, message);
+    }
+}
+
+// INTs are used as they are, the 64 bits don't all fit in a FLOAT.
+pub fn int_argument(arg: &VariableContent, func_name: &str) -> i64 {
+    match arg.data_type {
+        VariableTypes::INT => arg.value.parse().unwrap_or_else(|_| {
+            let message = format!(
+                "Function {} got an INT out of range: {}.",
+                func_name, arg.value
+            );
+            raise(ErrorKind::Value, message)
+        }),
+        _ => raise(
+            ErrorKind::Type,
+            format!(
+                "Function {} expects an INT, got {:?}.",
+                func_name, arg.data_type
+            ),
+        ),
+    }
+}
+
+// Gets the items of an argument that has to be a list.
+pub fn list_argument<'a>(arg: &'a VariableContent, func_name: &str) -> &'a Vec<VariableContent> {
+    arg.as_list().unwrap_or_else(|| {
+        raise(
+            ErrorKind::Type,
+            format!(
+                "Function {} expects a LIST, got {:?}.",
+                func_name, arg.data_type
+            ),
+        )
+    })
+}
@@ -0,0 +1,131 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::buildin_functions::{expect_args, int_argument, list_argument};
+use crate::errors::{raise, ErrorKind};
+use crate::host::Host;
+use crate::variables::{VariableContent, VariableTypes};
+
+// SplitMix64, small and the same on every platform, so a seed always gives the same numbers.
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    // Seeded from the clock when the script or the host doesn't give a seed.
+    pub fn from_time() -> Rng {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_nanos() as u64)
+            .unwrap_or(0);
+        Rng::new(nanos)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // Below the bound, numbers past the last whole multiple of it are drawn again so every
+    // number is as likely.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        let limit = u64::MAX - u64::MAX % bound;
+        loop {
+            let number = self.next_u64();
+            if number < limit {
+                return number % bound;
+            }
+        }
+    }
+
+    // From 0 up to but not including 1, with the 53 bits a float can hold.
+    pub fn next_float(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+// The functions of the random module, they use the generator of the host.
+pub fn random_rand_int(args: Vec<VariableContent>, host: &mut Host) -> VariableContent {
+    expect_args(&args, 2, "random.rand_int");
+    let low = int_argument(&args[0], "random.rand_int");
+    let high = int_argument(&args[1], "random.rand_int");
+    if low > high {
+        let message = format!("Function random.rand_int got {} above {}.", low, high);
+        raise(ErrorKind::Value, message);
+    }
+    // Both ends are included, a range of every INT draws a whole number.
+    let span = high.wrapping_sub(low) as u64;
+    let offset = match span.checked_add(1) {
+        Some(bound) => host.rng.below(bound),
+        None => host.rng.next_u64(),
+    };
+    let number = low.wrapping_add(offset as i64);
+    VariableContent::new(number.to_string(), VariableTypes::INT)
+}
+
+pub fn random_rand_float(args: Vec<VariableContent>, host: &mut Host) -> VariableContent {
+    expect_args(&args, 0, "random.rand_float");
+    VariableContent::new(host.rng.next_float().to_string(), VariableTypes::FLOAT)
+}
+
+pub fn random_choice(args: Vec<VariableContent>, host: &mut Host) -> VariableContent {
+    expect_args(&args, 1, "random.choice");
+    let items = list_argument(&args[0], "random.choice");
+    if items.is_empty() {
+        let message = "Function random.choice can't choose from an empty list.".to_string();
+        raise(ErrorKind::Value, message);
+    }
+    items[host.rng.below(items.len() as u64) as usize].clone()
+}
+
+// Gives a shuffled copy, lists are values like the others.
+pub fn random_shuffle(args: Vec<VariableContent>, host: &mut Host) -> VariableContent {
+    expect_args(&args, 1, "random.shuffle");
+    let mut items = list_argument(&args[0], "random.shuffle").clone();
+    for index in (1..items.len()).rev() {
+        let other = host.rng.below(index as u64 + 1) as usize;
+        items.swap(index, other);
+    }
+    VariableContent::list(items)
+}
+
+pub fn random_seed(args: Vec<VariableContent>, host: &mut Host) -> VariableContent {
+    expect_args(&args, 1, "random.seed");
+    host.rng = Rng::new(int_argument(&args[0], "random.seed") as u64);
+    VariableContent::null()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The numbers of the reference SplitMix64, a seed has to give the same numbers in every
+    // version.
+    #[test]
+    fn seed_gives_the_reference_numbers() {
+        let mut rng = Rng::new(0);
+        assert_eq!(rng.next_u64(), 0xE220_A839_7B1D_CDAF);
+        assert_eq!(rng.next_u64(), 0x6E78_9E6A_A1B9_65F4);
+        assert_eq!(rng.next_u64(), 0x06C4_5D18_8009_454F);
+        let mut rng = Rng::new(1_234_567);
+        assert_eq!(rng.next_u64(), 6_457_827_717_110_365_317);
+        assert_eq!(rng.next_u64(), 3_203_168_211_198_807_973);
+        assert_eq!(rng.next_u64(), 9_817_491_932_198_370_423);
+    }
+
+    #[test]
+    fn below_and_next_float_stay_in_range() {
+        let mut rng = Rng::new(42);
+        for _ in 0..1000 {
+            assert!(rng.below(6) < 6);
+            let float = rng.next_float();
+            assert!((0.0..1.0).contains(&float));
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use crate::buildin_functions::expect_args;
+use crate::errors::{raise, ErrorKind};
+use crate::limits::check_memory;
+use crate::variables::{VariableContent, VariableTypes};
+
+// String functions.
+pub fn simple_concat(args: Vec<VariableContent>) -> VariableContent {
+    check_memory(args.iter().map(|arg| arg.value.len()).sum());
+    let mut joined = String::new();
+    for arg in args {
+        joined.push_str(arg.value.as_str());
+    }
+
+    VariableContent::new(joined, VariableTypes::STRING)
+}
+
+// Gets the text of an argument that has to be a STRING.
+pub fn string_argument<'a>(arg: &'a VariableContent, func_name: &str) -> &'a str {
+    match arg.data_type {
+        VariableTypes::STRING => &arg.value,
+        _ => raise(
+            ErrorKind::Type,
+            format!(
+                "Function {} expects a STRING, got {:?}.",
+                func_name, arg.data_type
+            ),
+        ),
+    }
+}
+
+fn string(text: String) -> VariableContent {
+    VariableContent::new(text, VariableTypes::STRING)
+}
+
+// The functions of the str module.
+pub fn str_upper(args: Vec<VariableContent>) -> VariableContent {
+    expect_args(&args, 1, "str.upper");
+    string(string_argument(&args[0], "str.upper").to_uppercase())
+}
+
+pub fn str_lower(args: Vec<VariableContent>) -> VariableContent {
+    expect_args(&args, 1, "str.lower");
+    string(string_argument(&args[0], "str.lower").to_lowercase())
+}
+
+pub fn str_trim(args: Vec<VariableContent>) -> VariableContent {
+    expect_args(&args, 1, "str.trim");
+    string(string_argument(&args[0], "str.trim").trim().to_string())
+}
+
+// Counts characters, not bytes.
+pub fn str_len(args: Vec<VariableContent>) -> VariableContent {
+    expect_args(&args, 1, "str.len");
+    let length = string_argument(&args[0], "str.len").chars().count();
+    VariableContent::new(length.to_string(), VariableTypes::INT)
+}
+
+pub fn str_contains(args: Vec<VariableContent>) -> VariableContent {
+    expect_args(&args, 2, "str.contains");
+    let text = string_argument(&args[0], "str.contains");
+    let part = string_argument(&args[1], "str.contains");
+    VariableContent::new(text.contains(part).to_string(), VariableTypes::BOOL)
+}
+
+pub fn str_split(args: Vec<VariableContent>) -> VariableContent {
+    expect_args(&args, 2, "str.split");
+    let text = string_argument(&args[0], "str.split");
+    let separator = string_argument(&args[1], "str.split");
+    if separator.is_empty() {
+        raise(
+            ErrorKind::Value,
+            "The separator can't be empty.".to_string(),
+        );
+    }
+    let parts = text
+        .split(separator)
+        .map(|part| string(part.to_string()))
+        .collect();
+    VariableContent::list(parts)
+}
+
+pub fn str_replace(args: Vec<VariableContent>) -> VariableContent {
+    expect_args(&args, 3, "str.replace");
+    let text = string_argument(&args[0], "str.replace");
+    let from = string_argument(&args[1], "str.replace");
+    let to = string_argument(&args[2], "str.replace");
+    // An empty part is found before every character and at the end.
+    let found = match from.is_empty() {
+        true => text.chars().count() + 1,
+        false => text.matches(from).count(),
+    };
+    let kept = text.len() - found * from.len();
+    check_memory(kept.saturating_add(found.saturating_mul(to.len())));
+    string(text.replace(from, to))
+}
@@ -0,0 +1,39 @@
+use std::env;
+
+use crate::buildin_functions::expect_args;
+use crate::buildin_functions::string_functions::string_argument;
+use crate::host::Host;
+use crate::variables::{VariableContent, VariableTypes};
+
+// The value of an environment variable, NULL when it isn't set.
+pub fn simple_env(args: Vec<VariableContent>) -> VariableContent {
+    expect_args(&args, 1, "env");
+    match env::var(string_argument(&args[0], "env")) {
+        Ok(value) => VariableContent::new(value, VariableTypes::STRING),
+        Err(_) => VariableContent::null(),
+    }
+}
+
+fn line(line: Option<String>) -> VariableContent {
+    match line {
+        Some(line) => VariableContent::new(line, VariableTypes::STRING),
+        None => VariableContent::null(),
+    }
+}
+
+// Shows the prompt without a newline and reads the answer, NULL at the end of the input.
+pub fn simple_input(args: Vec<VariableContent>, host: &mut Host) -> VariableContent {
+    expect_args(&args, 1, "input");
+    host.write(&args[0].value);
+    line(host.read_line())
+}
+
+pub fn simple_read_line(args: Vec<VariableContent>, host: &mut Host) -> VariableContent {
+    expect_args(&args, 0, "read_line");
+    line(host.read_line())
+}
+
+pub fn simple_read_all(args: Vec<VariableContent>, host: &mut Host) -> VariableContent {
+    expect_args(&args, 0, "read_all");
+    VariableContent::new(host.read_all(), VariableTypes::STRING)
+}
@@ -0,0 +1,410 @@
+use crate::buildin_functions::string_functions::string_argument;
+use crate::buildin_functions::{expect_args, int_argument};
+use crate::errors::{raise, ErrorKind};
+use crate::host::Host;
+use crate::variables::{VariableContent, VariableTypes};
+
+const SECOND: i64 = 1000;
+const MINUTE: i64 = 60 * SECOND;
+const HOUR: i64 = 60 * MINUTE;
+const DAY: i64 = 24 * HOUR;
+
+// Times are INTs of milliseconds since the Unix epoch and durations INTs of milliseconds, so
+// they are compared and printed like other numbers.
+fn int(value: i64) -> VariableContent {
+    VariableContent::new(value.to_string(), VariableTypes::INT)
+}
+
+// The functions reading the clock of the host.
+pub fn time_now(args: Vec<VariableContent>, host: &mut Host) -> VariableContent {
+    expect_args(&args, 0, "time.now");
+    int(host.clock.now())
+}
+
+pub fn time_monotonic(args: Vec<VariableContent>, host: &mut Host) -> VariableContent {
+    expect_args(&args, 0, "time.monotonic");
+    int(host.clock.monotonic())
+}
+
+pub fn time_sleep(args: Vec<VariableContent>, host: &mut Host) -> VariableContent {
+    expect_args(&args, 1, "time.sleep");
+    let ms = int_argument(&args[0], "time.sleep");
+    if ms < 0 {
+        let message = format!("Function time.sleep can't sleep {} ms.", ms);
+        raise(ErrorKind::Value, message);
+    }
+    // Sleeps until the time limit at most, the executor raises the error once this returns.
+    let ms = match host.deadline {
+        Some(deadline) => ms.min(deadline.saturating_sub(host.clock.monotonic()).max(0) + 1),
+        None => ms,
+    };
+    host.clock.sleep(ms as u64);
+    VariableContent::null()
+}
+
+// Durations from other units, fractions like seconds(1.5) are rounded to a millisecond.
+fn duration(args: &[VariableContent], unit: i64, func_name: &str) -> VariableContent {
+    expect_args(args, 1, func_name);
+    let ms = match args[0].data_type {
+        VariableTypes::INT => int_argument(&args[0], func_name).checked_mul(unit),
+        // NaN and the infinities aren't in the range either.
+        VariableTypes::FLOAT => {
+            let amount: f64 = args[0].value.parse().unwrap();
+            let ms = (amount * unit as f64).round();
+            (ms >= i64::MIN as f64 && ms < i64::MAX as f64).then_some(ms as i64)
+        }
+        _ => raise(
+            ErrorKind::Type,
+            format!(
+                "Function {} expects a number, got {:?}.",
+                func_name, args[0].data_type
+            ),
+        ),
+    };
+    int(ms.unwrap_or_else(|| {
+        let message = format!(
+            "Function {} got {}, which is more milliseconds than an INT holds.",
+            func_name, args[0].value
+        );
+        raise(ErrorKind::Value, message)
+    }))
+}
+
+pub fn time_seconds(args: Vec<VariableContent>) -> VariableContent {
+    duration(&args, SECOND, "time.seconds")
+}
+
+pub fn time_minutes(args: Vec<VariableContent>) -> VariableContent {
+    duration(&args, MINUTE, "time.minutes")
+}
+
+pub fn time_hours(args: Vec<VariableContent>) -> VariableContent {
+    duration(&args, HOUR, "time.hours")
+}
+
+pub fn time_days(args: Vec<VariableContent>) -> VariableContent {
+    duration(&args, DAY, "time.days")
+}
+
+// The time a duration after another, a negative duration goes back.
+pub fn time_shift(args: Vec<VariableContent>) -> VariableContent {
+    expect_args(&args, 2, "time.shift");
+    let time = int_argument(&args[0], "time.shift");
+    let duration = int_argument(&args[1], "time.shift");
+    int(time.checked_add(duration).unwrap_or_else(|| {
+        raise(
+            ErrorKind::Value,
+            "Function time.shift went past the times an INT holds.".to_string(),
+        )
+    }))
+}
+
+// The duration from the first time to the second.
+pub fn time_between(args: Vec<VariableContent>) -> VariableContent {
+    expect_args(&args, 2, "time.between");
+    let start = int_argument(&args[0], "time.between");
+    let end = int_argument(&args[1], "time.between");
+    int(end.checked_sub(start).unwrap_or_else(|| {
+        raise(
+            ErrorKind::Value,
+            "Function time.between got times too far apart.".to_string(),
+        )
+    }))
+}
+
+// Like "1d 2h 3m 4.5s", units that are 0 are left out.
+pub fn time_format_duration(args: Vec<VariableContent>) -> VariableContent {
+    expect_args(&args, 1, "time.format_duration");
+    let ms = int_argument(&args[0], "time.format_duration");
+    let mut rest = ms.unsigned_abs();
+    let mut parts = Vec::new();
+    for (unit, suffix) in [(DAY, "d"), (HOUR, "h"), (MINUTE, "m")] {
+        let amount = rest / unit as u64;
+        rest %= unit as u64;
+        if amount > 0 {
+            parts.push(format!("{}{}", amount, suffix));
+        }
+    }
+    let (seconds, millis) = (rest / 1000, rest % 1000);
+    if millis > 0 {
+        let fraction = format!("{:03}", millis);
+        parts.push(format!("{}.{}s", seconds, fraction.trim_end_matches('0')));
+    } else if seconds > 0 || parts.is_empty() {
+        parts.push(format!("{}s", seconds));
+    }
+    let sign = if ms < 0 { "-" } else { "" };
+    VariableContent::new(
+        format!("{}{}", sign, parts.join(" ")),
+        VariableTypes::STRING,
+    )
+}
+
+// Like "2024-03-01T12:30:00.000Z", always in UTC with the milliseconds.
+pub fn time_to_iso(args: Vec<VariableContent>) -> VariableContent {
+    expect_args(&args, 1, "time.to_iso");
+    let ms = int_argument(&args[0], "time.to_iso");
+    let (days, ms_of_day) = (ms.div_euclid(DAY), ms.rem_euclid(DAY));
+    let (year, month, day) = civil_from_days(days);
+    if !(0..=9999).contains(&year) {
+        let message = format!("Function time.to_iso can't write the year {}.", year);
+        raise(ErrorKind::Value, message);
+    }
+    let text = format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        ms_of_day / HOUR,
+        ms_of_day % HOUR / MINUTE,
+        ms_of_day % MINUTE / SECOND,
+        ms_of_day % SECOND
+    );
+    VariableContent::new(text, VariableTypes::STRING)
+}
+
+// Reads a date like "2024-03-01", with a time like "T12:30", "T12:30:05" or "T12:30:05.25"
+// and an offset like "Z" or "+02:00". Without an offset the time is in UTC.
+pub fn time_from_iso(args: Vec<VariableContent>) -> VariableContent {
+    expect_args(&args, 1, "time.from_iso");
+    let text = string_argument(&args[0], "time.from_iso");
+    let mut reader = IsoReader { text, position: 0 };
+    let year = reader.number(4, "a year of 4 digits");
+    reader.expect('-');
+    let month = reader.number(2, "a month of 2 digits");
+    reader.expect('-');
+    let day = reader.number(2, "a day of 2 digits");
+    if !(1..=12).contains(&month) {
+        reader.invalid(&format!("there is no month {}", month));
+    }
+    if day < 1 || day > days_in_month(year, month) {
+        reader.invalid(&format!("there is no day {} in month {}", day, month));
+    }
+
+    let mut ms = days_from_civil(year, month, day) * DAY;
+    if reader.next_is(&['T', 't', ' ']) {
+        let hour = reader.number(2, "an hour of 2 digits");
+        reader.expect(':');
+        let minute = reader.number(2, "minutes of 2 digits");
+        let mut second = 0;
+        if reader.next_is(&[':']) {
+            second = reader.number(2, "seconds of 2 digits");
+            if reader.next_is(&['.', ',']) {
+                ms += reader.fraction();
+            }
+        }
+        if hour > 23 || minute > 59 || second > 59 {
+            reader.invalid("the time is out of range");
+        }
+        ms += hour * HOUR + minute * MINUTE + second * SECOND;
+
+        if reader.next_is(&['+', '-']) {
+            let sign = match &text[reader.position - 1..reader.position] {
+                "-" => -1,
+                _ => 1,
+            };
+            let hours = reader.number(2, "an offset of 2 digits");
+            reader.expect(':');
+            let minutes = reader.number(2, "offset minutes of 2 digits");
+            if hours > 23 || minutes > 59 {
+                reader.invalid("the offset is out of range");
+            }
+            // The offset is ahead of UTC, so UTC is behind the local time.
+            ms -= sign * (hours * HOUR + minutes * MINUTE);
+        } else {
+            reader.next_is(&['Z', 'z']);
+        }
+    }
+    if reader.position < text.len() {
+        reader.invalid(&format!("unexpected '{}'", &text[reader.position..]));
+    }
+    int(ms)
+}
+
+struct IsoReader<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl IsoReader<'_> {
+    fn invalid(&self, reason: &str) -> ! {
+        let message = format!("Invalid ISO-8601 time \"{}\": {}.", self.text, reason);
+        raise(ErrorKind::Value, message)
+    }
+
+    fn number(&mut self, digits: usize, expected: &str) -> i64 {
+        let end = self.position + digits;
+        match self.text.get(self.position..end) {
+            Some(part) if part.bytes().all(|byte| byte.is_ascii_digit()) => {
+                self.position = end;
+                part.parse().unwrap()
+            }
+            _ => self.invalid(&format!("expected {}", expected)),
+        }
+    }
+
+    fn expect(&mut self, expected: char) {
+        if !self.next_is(&[expected]) {
+            self.invalid(&format!("expected '{}'", expected));
+        }
+    }
+
+    // Moves past the next character when it is one of these.
+    fn next_is(&mut self, characters: &[char]) -> bool {
+        match self.text[self.position..].chars().next() {
+            Some(next) if characters.contains(&next) => {
+                self.position += next.len_utf8();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // The milliseconds of a fraction of a second, digits after them are dropped.
+    fn fraction(&mut self) -> i64 {
+        let digits: String = self.text[self.position..]
+            .chars()
+            .take_while(char::is_ascii_digit)
+            .collect();
+        if digits.is_empty() {
+            self.invalid("expected the digits of a fraction");
+        }
+        self.position += digits.len();
+        format!("{:0<3}", &digits[..digits.len().min(3)])
+            .parse()
+            .unwrap()
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// The days since 1970-01-01 of a date of the Gregorian calendar, from the algorithms of
+// Howard Hinnant that count in eras of 400 years starting in March.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::catch_error;
+
+    fn to_iso(ms: i64) -> String {
+        time_to_iso(vec![int(ms)]).value
+    }
+
+    fn from_iso(text: &str) -> i64 {
+        let text = VariableContent::new(text.to_string(), VariableTypes::STRING);
+        time_from_iso(vec![text]).value.parse().unwrap()
+    }
+
+    #[test]
+    fn times_around_leap_days_read_back() {
+        let times = [
+            ("2024-02-28T23:59:59.999Z", 1_709_164_799_999),
+            ("2024-02-29T00:00:00.000Z", 1_709_164_800_000),
+            ("2024-03-01T00:00:00.000Z", 1_709_251_200_000),
+            ("2000-02-29T12:00:00.000Z", 951_825_600_000),
+            ("2100-03-01T00:00:00.000Z", 4_107_542_400_000),
+            ("1970-01-01T00:00:00.000Z", 0),
+        ];
+        for (text, ms) in times {
+            assert_eq!(to_iso(ms), text);
+            assert_eq!(from_iso(text), ms);
+        }
+        // 1900 and 2100 aren't leap years, 2000 is.
+        assert_eq!(
+            to_iso(from_iso("2100-02-28T00:00:00Z") + DAY),
+            "2100-03-01T00:00:00.000Z"
+        );
+        assert_eq!(
+            to_iso(from_iso("1900-02-28T00:00:00Z") + DAY),
+            "1900-03-01T00:00:00.000Z"
+        );
+        assert_eq!(
+            to_iso(from_iso("2000-02-28T00:00:00Z") + DAY),
+            "2000-02-29T00:00:00.000Z"
+        );
+    }
+
+    #[test]
+    fn times_before_the_epoch_read_back() {
+        let times = [
+            ("1969-12-31T23:59:59.999Z", -1),
+            ("1969-12-31T00:00:00.000Z", -DAY),
+            ("1900-01-01T00:00:00.000Z", -2_208_988_800_000),
+            ("0000-01-01T00:00:00.000Z", -62_167_219_200_000),
+            ("1968-02-29T06:30:00.500Z", -58_037_399_500),
+        ];
+        for (text, ms) in times {
+            assert_eq!(to_iso(ms), text);
+            assert_eq!(from_iso(text), ms);
+        }
+        assert_eq!(from_iso("1970-01-01T01:00:00+02:00"), -HOUR);
+    }
+
+    #[test]
+    fn durations_past_an_int_raise() {
+        let seconds = |text: &str, data_type| {
+            let amount = VariableContent::new(text.to_string(), data_type);
+            catch_error(|| time_seconds(vec![amount]).value)
+        };
+        let largest = seconds("9223372036854775", VariableTypes::INT);
+        assert_eq!(largest.unwrap(), "9223372036854775000");
+        assert_eq!(seconds("-1.5", VariableTypes::FLOAT).unwrap(), "-1500");
+        let too_long = [
+            ("9223372036854775807", VariableTypes::INT),
+            ("-9223372036854776", VariableTypes::INT),
+            ("9223372036854775.807", VariableTypes::FLOAT),
+            ("-1e300", VariableTypes::FLOAT),
+            ("inf", VariableTypes::FLOAT),
+            ("NaN", VariableTypes::FLOAT),
+        ];
+        for (text, data_type) in too_long {
+            let error = seconds(text, data_type).unwrap_err();
+            assert_eq!(error.kind, ErrorKind::Value, "{}", text);
+        }
+    }
+
+    #[test]
+    fn every_day_of_four_years_reads_back() {
+        let start = from_iso("1967-01-01");
+        for day in 0..4 * 366 {
+            let ms = start + day * DAY + 1234;
+            assert_eq!(from_iso(&to_iso(ms)), ms);
+        }
+    }
+}
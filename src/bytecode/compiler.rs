@@ -0,0 +1,1017 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::ast::{CatchClause, Expression, FunctionDecl, KeywordArg, Slot, Span, Statement};
+use crate::bytecode::value::Value;
+use crate::bytecode::{
+    Capture, Chunk, CompiledFunction, CompiledModule, DebugVariable, ExportSlot, FunctionEntry,
+    Instruction, Operator, Program, SlotLocation,
+};
+use crate::errors::ErrorKind;
+use crate::functions::{Builtin, FunctionContainer};
+use crate::modules::{exports, Export, Module};
+use crate::variables::{VariableContent, VariableTypes};
+
+// Compiles the resolved files of a program, the builtins of the container are looked up once
+// here. The globals of every file get their own slots.
+pub fn compile_program(modules: &[Module], function_container: &FunctionContainer) -> Program {
+    let mut compiler = Compiler {
+        function_container,
+        scopes: vec![Scope::default()],
+        captures: vec![Vec::new()],
+        module: 0,
+        uses: Vec::new(),
+        global_base: 0,
+        global_names: Vec::new(),
+        declared_globals: Vec::new(),
+        function_entries: HashMap::new(),
+        entries: Vec::new(),
+    };
+
+    let mut global_names = Vec::new();
+    let mut compiled = Vec::new();
+    for (index, module) in modules.iter().enumerate() {
+        // Functions can use globals that are declared below them, so all of them get a slot
+        // first. These are in the order the resolver indexed them.
+        compiler.module = index;
+        compiler.uses = module.uses.clone();
+        compiler.global_base = global_names.len() as u32;
+        compiler.global_names = declared_names(&module.program);
+        compiler.declared_globals.clear();
+
+        let mut state = FunctionState::new(contains_literal(&module.program), Span::default());
+        compiler.compile_statements(&mut state, &module.program);
+        let script = compiler.finish_function(state, None);
+        let exports = exports(&module.program)
+            .into_iter()
+            .map(|(name, export)| {
+                let slot = match export {
+                    Export::Function(_) => ExportSlot::Function(compiler.function_entry(name)),
+                    Export::Constant(index) => {
+                        ExportSlot::Constant(compiler.global_base + index as u32)
+                    }
+                };
+                (name.to_string(), slot)
+            })
+            .collect();
+        compiled.push(Rc::new(CompiledModule {
+            path: module.path.clone(),
+            script,
+            exports,
+            uses: module.uses.clone(),
+        }));
+        global_names.append(&mut compiler.global_names);
+    }
+
+    Program {
+        modules: compiled,
+        global_names,
+        function_entries: compiler.entries,
+        capabilities: function_container.capabilities().clone(),
+    }
+}
+
+// What the compiler knows about a local variable.
+#[derive(Clone)]
+struct Local {
+    name: String,
+    slot: u32,
+    constant: bool,
+    annotation: Option<VariableTypes>,
+    declared: bool, // Whether the line declaring it was compiled already.
+}
+
+// The scopes match the ones of the resolver, the first one holds the globals.
+#[derive(Default)]
+struct Scope {
+    function: usize, // How deep the function it belongs to is nested, the script is 0.
+    locals: Vec<Local>,
+}
+
+// Where a variable the resolver found lives.
+enum Location {
+    Global(u32),
+    Local(Local),
+    Upvalue(u32),
+}
+
+// A try statement around the code being compiled, a return has to leave it.
+#[derive(Clone)]
+struct TryRegion {
+    handler: bool, // Whether its Try is active.
+    finally: Option<Vec<Statement>>,
+    depth: usize, // The number of scopes around the try statement.
+}
+
+// The function being compiled.
+struct FunctionState {
+    chunk: Chunk,
+    next_slot: u32,
+    slot_count: usize,
+    boxed: bool,
+    span: Span, // Of the statement being compiled.
+    tries: Vec<TryRegion>,
+}
+
+impl FunctionState {
+    fn new(boxed: bool, span: Span) -> FunctionState {
+        FunctionState {
+            chunk: Chunk::default(),
+            next_slot: 0,
+            slot_count: 0,
+            boxed,
+            span,
+            tries: Vec::new(),
+        }
+    }
+
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.chunk.code.push(instruction);
+        self.chunk.spans.push(self.span);
+        self.chunk.code.len() - 1
+    }
+
+    fn add_constant(&mut self, value: VariableContent) -> u32 {
+        self.chunk.constants.push(Value::constant(value));
+        (self.chunk.constants.len() - 1) as u32
+    }
+
+    fn add_name(&mut self, name: String) -> u32 {
+        self.chunk.names.push(name);
+        (self.chunk.names.len() - 1) as u32
+    }
+
+    fn allocate_slot(&mut self) -> u32 {
+        let slot = self.next_slot;
+        self.next_slot += 1;
+        self.slot_count = self.slot_count.max(self.next_slot as usize);
+        slot
+    }
+}
+
+struct Compiler<'a> {
+    function_container: &'a FunctionContainer,
+    scopes: Vec<Scope>,
+    captures: Vec<Vec<((usize, usize), Capture)>>, // Per function, by scope and index captured.
+    module: usize,                                 // The index of the file being compiled.
+    uses: Vec<String>,                             // The builtin modules it uses.
+    global_base: u32,                              // The slot of its first global.
+    global_names: Vec<String>,
+    declared_globals: Vec<String>, // In the order the script declares them, for debug.var.
+    function_entries: HashMap<(usize, String), u32>,
+    entries: Vec<FunctionEntry>,
+}
+
+impl Compiler<'_> {
+    // Every file has its own functions.
+    fn function_entry(&mut self, name: &str) -> u32 {
+        let key = (self.module, name.to_string());
+        if let Some(entry) = self.function_entries.get(&key) {
+            return *entry;
+        }
+        let entry = self.entries.len() as u32;
+        self.function_entries.insert(key, entry);
+        self.entries.push(FunctionEntry {
+            name: name.to_string(),
+            module: self.module,
+            builtin: self
+                .function_container
+                .builtin_name(name, &self.uses)
+                .and_then(|name| self.function_container.get_builtin(&name)),
+        });
+        entry
+    }
+
+    fn finish_function(
+        &mut self,
+        mut state: FunctionState,
+        decl: Option<Rc<FunctionDecl>>,
+    ) -> Rc<CompiledFunction> {
+        // Returns NULL when the end is reached without a return statement.
+        let null = state.add_constant(VariableContent::null());
+        state.emit(Instruction::Constant(null));
+        state.emit(Instruction::Return);
+        let captures = self.captures.last().unwrap();
+        Rc::new(CompiledFunction {
+            decl,
+            module: self.module,
+            chunk: state.chunk,
+            slot_count: state.slot_count,
+            boxed: state.boxed,
+            captures: captures.iter().map(|(_, capture)| *capture).collect(),
+        })
+    }
+
+    // Named functions only see the globals, function literals also see the scopes around them.
+    fn compile_function(&mut self, decl: &Rc<FunctionDecl>, literal: bool) -> Rc<CompiledFunction> {
+        let (outer_scopes, outer_captures) = match literal {
+            true => (None, None),
+            false => {
+                let scopes = std::mem::replace(&mut self.scopes, vec![Scope::default()]);
+                let captures = std::mem::replace(&mut self.captures, vec![Vec::new()]);
+                (Some(scopes), Some(captures))
+            }
+        };
+
+        let function = self.captures.len();
+        self.captures.push(Vec::new());
+        let defaults = decl
+            .params
+            .iter()
+            .filter_map(|param| param.default.as_ref());
+        let boxed = contains_literal(&decl.body) || defaults.clone().any(contains_function);
+        let mut state = FunctionState::new(boxed, decl.span);
+        let params: Vec<Local> = decl
+            .params
+            .iter()
+            .map(|param| (param.name.clone(), param.annotation))
+            .chain(decl.rest.iter().map(|rest| (rest.clone(), None)))
+            .map(|(name, annotation)| Local {
+                name,
+                slot: state.allocate_slot(),
+                constant: false,
+                annotation,
+                declared: true,
+            })
+            .collect();
+        self.scopes.push(Scope {
+            function,
+            locals: params.clone(),
+        });
+
+        // Default values can use the parameters before them, so the parameters are moved into
+        // cells one by one.
+        let defaults = decl.params.iter().map(|param| param.default.as_ref());
+        for (index, (local, default)) in params.iter().zip(defaults.chain(None)).enumerate() {
+            if let Some(default) = default {
+                let param = index as u32;
+                let skip = state.emit(Instruction::SkipDefault { param, target: 0 });
+                self.compile_expression(&mut state, default);
+                state.emit(Instruction::CheckArgument(param));
+                state.emit(Instruction::SetLocal(local.slot));
+                let target = state.chunk.code.len() as u32;
+                state.chunk.code[skip] = Instruction::SkipDefault { param, target };
+            }
+            if state.boxed {
+                let name = state.add_name(local.name.clone());
+                state.emit(Instruction::NewCell(local.slot));
+                state.emit(Instruction::GetLocal(local.slot));
+                state.emit(Instruction::DefineCell {
+                    slot: local.slot,
+                    name,
+                    constant: false,
+                    annotation: local.annotation,
+                });
+            }
+        }
+        // The body shares the scope of the parameters.
+        self.declare_locals(&mut state, &decl.body);
+
+        self.compile_statements(&mut state, &decl.body);
+        let compiled = self.finish_function(state, Some(decl.clone()));
+        self.scopes.pop();
+        self.captures.pop();
+
+        if let (Some(scopes), Some(captures)) = (outer_scopes, outer_captures) {
+            self.scopes = scopes;
+            self.captures = captures;
+        }
+        compiled
+    }
+
+    // Every variable the block declares gets its slot up front, function literals in the block
+    // can use the ones declared after them.
+    fn scope_in(&mut self, state: &mut FunctionState, statements: &[Statement]) {
+        self.scopes.push(Scope {
+            function: self.captures.len() - 1,
+            locals: Vec::new(),
+        });
+        self.declare_locals(state, statements);
+    }
+
+    fn declare_locals(&mut self, state: &mut FunctionState, statements: &[Statement]) {
+        for name in declared_names(statements) {
+            let locals = &mut self.scopes.last_mut().unwrap().locals;
+            if locals.iter().any(|local| local.name == name) {
+                continue;
+            }
+            let local = Local {
+                name,
+                slot: state.allocate_slot(),
+                constant: false,
+                annotation: None,
+                declared: false,
+            };
+            if state.boxed {
+                state.emit(Instruction::NewCell(local.slot));
+            }
+            locals.push(local);
+        }
+    }
+
+    // The slots of the scope are free to be used by the next one.
+    fn scope_out(&mut self, state: &mut FunctionState) {
+        let scope = self.scopes.pop().unwrap();
+        state.next_slot -= scope.locals.len() as u32;
+    }
+
+    fn locate(&mut self, slot: Slot) -> Location {
+        let level = self.scopes.len() - 1 - slot.depth;
+        if level == 0 {
+            return Location::Global(self.global_base + slot.index as u32);
+        }
+
+        let function = self.captures.len() - 1;
+        if self.scopes[level].function == function {
+            Location::Local(self.scopes[level].locals[slot.index].clone())
+        } else {
+            Location::Upvalue(self.upvalue(function, level, slot.index))
+        }
+    }
+
+    // Captures the variable from the function around this one, which might capture it as well.
+    fn upvalue(&mut self, function: usize, level: usize, index: usize) -> u32 {
+        let captures = &self.captures[function];
+        if let Some(upvalue) = captures.iter().position(|(key, _)| *key == (level, index)) {
+            return upvalue as u32;
+        }
+
+        let capture = match self.scopes[level].function == function - 1 {
+            true => Capture::Cell(self.scopes[level].locals[index].slot),
+            false => Capture::Upvalue(self.upvalue(function - 1, level, index)),
+        };
+        self.captures[function].push(((level, index), capture));
+        (self.captures[function].len() - 1) as u32
+    }
+
+    fn compile_statements(&mut self, state: &mut FunctionState, statements: &[Statement]) {
+        for statement in statements {
+            self.compile_statement(state, statement);
+        }
+    }
+
+    fn compile_statement(&mut self, state: &mut FunctionState, statement: &Statement) {
+        let outer_span = std::mem::replace(&mut state.span, statement.span());
+        self.compile_statement_code(state, statement);
+        state.span = outer_span;
+    }
+
+    fn compile_statement_code(&mut self, state: &mut FunctionState, statement: &Statement) {
+        match statement {
+            Statement::VarDecl {
+                name,
+                annotation,
+                constant,
+                value,
+                index,
+                ..
+            } => {
+                match value {
+                    Some(value) => self.compile_expression(state, value),
+                    None => {
+                        let null = state.add_constant(VariableContent::null());
+                        state.emit(Instruction::Constant(null));
+                    }
+                }
+
+                let index = index.get().expect("Variable wasn't resolved.");
+                if self.scopes.len() == 1 {
+                    if !self.declared_globals.contains(name) {
+                        self.declared_globals.push(name.clone());
+                    }
+                    state.emit(Instruction::DefineGlobal {
+                        slot: self.global_base + index as u32,
+                        constant: *constant,
+                        annotation: *annotation,
+                    });
+                    return;
+                }
+
+                // Redeclaring in the same scope reuses the slot.
+                let local = &mut self.scopes.last_mut().unwrap().locals[index];
+                if local.declared && local.constant && !state.boxed {
+                    panic!("Couldn't declare {} as it is already a constant.", name);
+                }
+                local.constant = *constant;
+                local.annotation = *annotation;
+                local.declared = true;
+                let slot = local.slot;
+
+                if state.boxed {
+                    let name = state.add_name(name.clone());
+                    state.emit(Instruction::DefineCell {
+                        slot,
+                        name,
+                        constant: *constant,
+                        annotation: *annotation,
+                    });
+                } else {
+                    self.compile_check_type(state, name, *annotation);
+                    state.emit(Instruction::SetLocal(slot));
+                }
+            }
+            Statement::Assign {
+                name, value, slot, ..
+            } => {
+                self.compile_expression(state, value);
+                self.compile_store(state, name, slot.get());
+            }
+            // Sets the field in a copy of the struct, which is stored in the variable.
+            Statement::FieldAssign {
+                name,
+                path,
+                value,
+                slot,
+                ..
+            } => {
+                self.compile_expression(state, value);
+                self.compile_variable(state, name, slot.get());
+                state.chunk.field_paths.push(path.clone());
+                let path = (state.chunk.field_paths.len() - 1) as u32;
+                state.emit(Instruction::SetField(path));
+                self.compile_store(state, name, slot.get());
+            }
+            // The resolver gave every struct literal and variant its declaration.
+            Statement::StructDecl(_) | Statement::EnumDecl(_) => {}
+            Statement::FunctionDecl(decl) => {
+                let entry = self.function_entry(&decl.name);
+                let compiled = self.compile_function(decl, false);
+                state.chunk.functions.push(compiled);
+                let function = (state.chunk.functions.len() - 1) as u32;
+                state.emit(Instruction::DeclareFunction { entry, function });
+            }
+            Statement::Return { value, .. } => {
+                match value {
+                    Some(value) => self.compile_expression(state, value),
+                    None => {
+                        let null = state.add_constant(VariableContent::null());
+                        state.emit(Instruction::Constant(null));
+                    }
+                }
+                self.compile_leave_tries(state);
+                state.emit(Instruction::Return);
+            }
+            Statement::If {
+                condition, body, ..
+            } => {
+                self.compile_expression(state, condition);
+                let jump = state.emit(Instruction::JumpUnlessTrue(0));
+                self.scope_in(state, body);
+                self.compile_statements(state, body);
+                self.scope_out(state);
+                let end = state.chunk.code.len() as u32;
+                state.chunk.code[jump] = Instruction::JumpUnlessTrue(end);
+            }
+            // Every arm tests its pattern against the value on the stack, the arm that matches
+            // pops it and jumps to the end after its body.
+            Statement::Match { value, arms, .. } => {
+                self.compile_expression(state, value);
+                let mut ends = Vec::new();
+                for arm in arms {
+                    let bindings = arm.pattern.bindings();
+                    let locals = bindings
+                        .into_iter()
+                        .map(|name| Local {
+                            name,
+                            slot: state.allocate_slot(),
+                            constant: false,
+                            annotation: None,
+                            declared: true,
+                        })
+                        .collect::<Vec<Local>>();
+                    let slots = locals.iter().map(|local| local.slot).collect();
+                    self.scopes.push(Scope {
+                        function: self.captures.len() - 1,
+                        locals,
+                    });
+                    self.declare_locals(state, &arm.body);
+
+                    state.chunk.patterns.push((arm.pattern.clone(), slots));
+                    let pattern = (state.chunk.patterns.len() - 1) as u32;
+                    let test = state.emit(Instruction::Match { pattern, next: 0 });
+                    self.compile_statements(state, &arm.body);
+                    self.scope_out(state);
+                    ends.push(state.emit(Instruction::Jump(0)));
+                    let next = state.chunk.code.len() as u32;
+                    state.chunk.code[test] = Instruction::Match { pattern, next };
+                }
+                state.emit(Instruction::NoMatch);
+                let end = state.chunk.code.len() as u32;
+                for jump in ends {
+                    state.chunk.code[jump] = Instruction::Jump(end);
+                }
+            }
+            Statement::Throw { value, .. } => {
+                self.compile_expression(state, value);
+                state.emit(Instruction::Throw);
+            }
+            Statement::Try {
+                body,
+                catch,
+                finally,
+                ..
+            } => self.compile_try(state, body, catch.as_ref(), finally.as_ref()),
+            Statement::Expression(expression) => {
+                self.compile_expression(state, expression);
+                state.emit(Instruction::Pop);
+            }
+            // Imports are only at the top level, the module is a constant global.
+            Statement::Import {
+                name,
+                module,
+                index,
+                ..
+            } => {
+                let module = module.get().expect("Import wasn't loaded.");
+                let index = index.get().expect("Variable wasn't resolved.");
+                if !self.declared_globals.contains(name) {
+                    self.declared_globals.push(name.clone());
+                }
+                state.emit(Instruction::Import(module as u32));
+                state.emit(Instruction::DefineGlobal {
+                    slot: self.global_base + index as u32,
+                    constant: true,
+                    annotation: None,
+                });
+            }
+            // The compiler looks up the builtins of the used modules for the whole file.
+            Statement::Use { .. } => {}
+            Statement::DebugVar { .. } => {
+                let mut variables: Vec<DebugVariable> = match self.scopes.len() {
+                    // The VM knows if globals are constant, they might be used before their line.
+                    1 => self
+                        .declared_globals
+                        .iter()
+                        .map(|name| DebugVariable {
+                            name: name.clone(),
+                            location: SlotLocation::Global(
+                                self.global_base
+                                    + self.global_names.iter().position(|n| n == name).unwrap()
+                                        as u32,
+                            ),
+                            constant: false,
+                            annotation: None,
+                        })
+                        .collect(),
+                    _ => self
+                        .scopes
+                        .last()
+                        .unwrap()
+                        .locals
+                        .iter()
+                        .filter(|local| local.declared)
+                        .map(|local| DebugVariable {
+                            name: local.name.clone(),
+                            location: SlotLocation::Local(local.slot),
+                            constant: local.constant,
+                            annotation: local.annotation,
+                        })
+                        .collect(),
+                };
+                variables.sort_by(|a, b| a.name.cmp(&b.name));
+                state.chunk.debug_scopes.push(variables);
+                let index = (state.chunk.debug_scopes.len() - 1) as u32;
+                state.emit(Instruction::DebugVar(index));
+            }
+        }
+    }
+
+    fn compile_block(&mut self, state: &mut FunctionState, statements: &[Statement]) {
+        self.scope_in(state, statements);
+        self.compile_statements(state, statements);
+        self.scope_out(state);
+    }
+
+    // The body is guarded by a Try that jumps to the catch block, which gets the error as its
+    // first local. The finally block is compiled after every way out of the statement: the end
+    // of the body, the end of the catch block, the errors the catch block doesn't catch and the
+    // returns in both.
+    fn compile_try(
+        &mut self,
+        state: &mut FunctionState,
+        body: &[Statement],
+        catch: Option<&CatchClause>,
+        finally: Option<&Vec<Statement>>,
+    ) {
+        let handler = state.emit(Instruction::Try(0));
+        state.tries.push(TryRegion {
+            handler: true,
+            finally: finally.cloned(),
+            depth: self.scopes.len(),
+        });
+        self.compile_block(state, body);
+        state.tries.pop();
+        state.emit(Instruction::EndTry);
+        if let Some(finally) = finally {
+            self.compile_block(state, finally);
+        }
+        let mut ends = vec![state.emit(Instruction::Jump(0))];
+        state.chunk.code[handler] = Instruction::Try(state.chunk.code.len() as u32);
+
+        match catch {
+            Some(catch) => ends.extend(self.compile_catch(state, catch, finally)),
+            None => self.compile_finally_and_throw(state, finally.unwrap()),
+        }
+        let end = state.chunk.code.len() as u32;
+        for jump in ends {
+            state.chunk.code[jump] = Instruction::Jump(end);
+        }
+    }
+
+    // Stores the error on the stack in the first local of the catch block and runs it, gives
+    // back the jump to the end of the try statement.
+    fn compile_catch(
+        &mut self,
+        state: &mut FunctionState,
+        catch: &CatchClause,
+        finally: Option<&Vec<Statement>>,
+    ) -> Option<usize> {
+        let local = Local {
+            name: catch.name.clone(),
+            slot: state.allocate_slot(),
+            constant: false,
+            annotation: None,
+            declared: true,
+        };
+        let slot = local.slot;
+        self.scopes.push(Scope {
+            function: self.captures.len() - 1,
+            locals: vec![local],
+        });
+        self.declare_locals(state, &catch.body);
+        if state.boxed {
+            let name = state.add_name(catch.name.clone());
+            state.emit(Instruction::NewCell(slot));
+            state.emit(Instruction::DefineCell {
+                slot,
+                name,
+                constant: false,
+                annotation: None,
+            });
+        } else {
+            state.emit(Instruction::SetLocal(slot));
+        }
+
+        // Errors of the catch block still run the finally block.
+        let rethrow = finally.map(|_| state.emit(Instruction::Try(0)));
+        state.tries.push(TryRegion {
+            handler: rethrow.is_some(),
+            finally: finally.cloned(),
+            depth: self.scopes.len() - 1,
+        });
+        self.compile_statements(state, &catch.body);
+        state.tries.pop();
+        self.scope_out(state);
+        let (rethrow, finally) = match (rethrow, finally) {
+            (Some(rethrow), Some(finally)) => (rethrow, finally),
+            _ => return None,
+        };
+        state.emit(Instruction::EndTry);
+        self.compile_block(state, finally);
+        let end = state.emit(Instruction::Jump(0));
+        state.chunk.code[rethrow] = Instruction::Try(state.chunk.code.len() as u32);
+        self.compile_finally_and_throw(state, finally);
+        Some(end)
+    }
+
+    // Raises the error on the stack again after the finally block. The error waits in a slot,
+    // so a return in the finally block leaves nothing on the stack.
+    fn compile_finally_and_throw(&mut self, state: &mut FunctionState, finally: &[Statement]) {
+        let slot = state.allocate_slot();
+        state.emit(Instruction::SetLocal(slot));
+        self.compile_block(state, finally);
+        state.emit(Instruction::GetLocal(slot));
+        state.emit(Instruction::Throw);
+        state.next_slot -= 1;
+    }
+
+    // Runs the finally blocks of the try statements a return leaves, the innermost first.
+    // The returned value waits in a slot while they run.
+    fn compile_leave_tries(&mut self, state: &mut FunctionState) {
+        if state.tries.is_empty() {
+            return;
+        }
+        let slot = state.allocate_slot();
+        state.emit(Instruction::SetLocal(slot));
+        let tries = std::mem::take(&mut state.tries);
+        for (i, region) in tries.iter().enumerate().rev() {
+            if region.handler {
+                state.emit(Instruction::EndTry);
+            }
+            // The finally block is compiled in the scopes of the try statement, a return in it
+            // only leaves the try statements around that.
+            if let Some(finally) = &region.finally {
+                state.tries = tries[..i].to_vec();
+                let inner_scopes = self.scopes.split_off(region.depth);
+                self.compile_block(state, finally);
+                self.scopes.extend(inner_scopes);
+            }
+        }
+        state.tries = tries;
+        state.emit(Instruction::GetLocal(slot));
+        state.next_slot -= 1;
+    }
+
+    // Stores the value on top of the stack in the variable.
+    fn compile_store(&mut self, state: &mut FunctionState, name: &str, slot: Option<Slot>) {
+        let location = slot.map(|slot| self.locate(slot));
+        match location {
+            Some(Location::Global(slot)) => {
+                state.emit(Instruction::SetGlobal(slot));
+            }
+            Some(Location::Local(local)) if state.boxed => {
+                let name = state.add_name(name.to_string());
+                state.emit(Instruction::SetCell {
+                    slot: local.slot,
+                    name,
+                });
+            }
+            Some(Location::Local(local)) => {
+                if local.constant {
+                    panic!("Couldn't set {} as it is a constant.", name);
+                }
+                self.compile_check_type(state, name, local.annotation);
+                state.emit(Instruction::SetLocal(local.slot));
+            }
+            Some(Location::Upvalue(index)) => {
+                let name = state.add_name(name.to_string());
+                state.emit(Instruction::SetUpvalue { index, name });
+            }
+            None => {
+                let message = state.add_name(format!(
+                    "Couldn't set variable {} as it doesn't exist.",
+                    name
+                ));
+                let kind = ErrorKind::UnknownVariable;
+                state.emit(Instruction::Fail { kind, message });
+            }
+        }
+    }
+
+    fn compile_check_type(
+        &mut self,
+        state: &mut FunctionState,
+        name: &str,
+        annotation: Option<VariableTypes>,
+    ) {
+        if let Some(expected) = annotation {
+            let name = state.add_name(name.to_string());
+            state.emit(Instruction::CheckType { expected, name });
+        }
+    }
+
+    fn compile_variable(&mut self, state: &mut FunctionState, name: &str, slot: Option<Slot>) {
+        let location = slot.map(|slot| self.locate(slot));
+        match location {
+            Some(Location::Global(slot)) => {
+                state.emit(Instruction::GetGlobal(slot));
+            }
+            Some(Location::Local(local)) if state.boxed => {
+                let name = state.add_name(name.to_string());
+                state.emit(Instruction::GetCell {
+                    slot: local.slot,
+                    name,
+                });
+            }
+            Some(Location::Local(local)) => {
+                state.emit(Instruction::GetLocal(local.slot));
+            }
+            Some(Location::Upvalue(index)) => {
+                let name = state.add_name(name.to_string());
+                state.emit(Instruction::GetUpvalue { index, name });
+            }
+            None => {
+                let message = state.add_name(format!("Couldn't get variable {}.", name));
+                let kind = ErrorKind::UnknownVariable;
+                state.emit(Instruction::Fail { kind, message });
+            }
+        }
+    }
+
+    // Pushes the positional arguments and then the keyword ones, whose names are added to the
+    // chunk when there are any.
+    fn compile_arguments(
+        &mut self,
+        state: &mut FunctionState,
+        args: &[Expression],
+        keywords: &[KeywordArg],
+    ) -> Option<u32> {
+        for arg in args {
+            self.compile_expression(state, arg);
+        }
+        if keywords.is_empty() {
+            return None;
+        }
+        for keyword in keywords {
+            self.compile_expression(state, &keyword.value);
+        }
+        let names = keywords
+            .iter()
+            .map(|keyword| keyword.name.clone())
+            .collect();
+        state.chunk.keyword_names.push(names);
+        Some((state.chunk.keyword_names.len() - 1) as u32)
+    }
+
+    fn compile_expression(&mut self, state: &mut FunctionState, expression: &Expression) {
+        match expression {
+            Expression::Literal { value, .. } => {
+                let constant = state.add_constant(value.clone());
+                state.emit(Instruction::Constant(constant));
+            }
+            Expression::Variable { name, slot, .. } => {
+                self.compile_variable(state, name, slot.get());
+            }
+            // Calling a function of an imported module.
+            Expression::Call {
+                namespace: Some(namespace),
+                name,
+                args,
+                keywords,
+                slot,
+                ..
+            } if slot.get().is_some() => {
+                self.compile_variable(state, namespace, slot.get());
+                let field = state.add_name(name.clone());
+                state.emit(Instruction::GetField(field));
+                let keywords = self.compile_arguments(state, args, keywords);
+                let name = state.add_name(format!("{}.{}", namespace, name));
+                state.emit(Instruction::CallValue {
+                    args: args.len() as u32,
+                    keywords,
+                    name,
+                });
+            }
+            // Calling a variable holding a function.
+            Expression::Call {
+                name,
+                args,
+                keywords,
+                slot,
+                ..
+            } if slot.get().is_some() => {
+                self.compile_variable(state, name, slot.get());
+                let keywords = self.compile_arguments(state, args, keywords);
+                let name = state.add_name(name.clone());
+                state.emit(Instruction::CallValue {
+                    args: args.len() as u32,
+                    keywords,
+                    name,
+                });
+            }
+            // The name of a function in a builtin module includes the module.
+            Expression::Call {
+                namespace,
+                name,
+                args,
+                keywords,
+                ..
+            } => {
+                let keywords = self.compile_arguments(state, args, keywords);
+                let entry = match namespace {
+                    Some(namespace) => self.function_entry(&format!("{}.{}", namespace, name)),
+                    None => self.function_entry(name),
+                };
+                let operator = match (namespace, name.as_str()) {
+                    (None, "add") => Some(Operator::Add),
+                    (None, "sub") => Some(Operator::Sub),
+                    (None, "eq") => Some(Operator::Eq),
+                    _ => None,
+                };
+                let simple = matches!(
+                    self.entries[entry as usize].builtin,
+                    Some(Builtin::Simple(_))
+                );
+                if let (Some(operator), None, true) = (operator, keywords, simple) {
+                    state.emit(Instruction::Operator {
+                        operator,
+                        entry,
+                        args: args.len() as u32,
+                    });
+                } else {
+                    state.emit(Instruction::Call {
+                        entry,
+                        args: args.len() as u32,
+                        keywords,
+                    });
+                }
+            }
+            Expression::List { items, .. } => {
+                for item in items {
+                    self.compile_expression(state, item);
+                }
+                state.emit(Instruction::MakeList(items.len() as u32));
+            }
+            Expression::Struct { fields, decl, .. } => {
+                for field in fields {
+                    self.compile_expression(state, &field.value);
+                }
+                let decl = decl.borrow().clone().expect("Struct wasn't resolved.");
+                let names = fields.iter().map(|field| field.name.clone()).collect();
+                state.chunk.struct_literals.push((decl, names));
+                let index = (state.chunk.struct_literals.len() - 1) as u32;
+                state.emit(Instruction::MakeStruct(index));
+            }
+            Expression::Variant {
+                variant,
+                args,
+                decl,
+                ..
+            } => {
+                for arg in args {
+                    self.compile_expression(state, arg);
+                }
+                let decl = decl.borrow().clone().expect("Enum wasn't resolved.");
+                state
+                    .chunk
+                    .variants
+                    .push((decl, variant.clone(), args.len()));
+                let index = (state.chunk.variants.len() - 1) as u32;
+                state.emit(Instruction::MakeVariant(index));
+            }
+            Expression::Field { value, field, .. } => {
+                self.compile_expression(state, value);
+                let name = state.add_name(field.clone());
+                state.emit(Instruction::GetField(name));
+            }
+            Expression::Function(decl) => {
+                let compiled = self.compile_function(decl, true);
+                state.chunk.functions.push(compiled);
+                let function = (state.chunk.functions.len() - 1) as u32;
+                state.emit(Instruction::MakeClosure(function));
+            }
+        }
+    }
+}
+
+// Whether the expression creates a function literal.
+fn contains_function(expression: &Expression) -> bool {
+    match expression {
+        Expression::Function(_) => true,
+        Expression::Call { args, keywords, .. } => {
+            args.iter().any(contains_function)
+                || keywords
+                    .iter()
+                    .any(|keyword| contains_function(&keyword.value))
+        }
+        Expression::List { items, .. } => items.iter().any(contains_function),
+        Expression::Struct { fields, .. } => {
+            fields.iter().any(|field| contains_function(&field.value))
+        }
+        Expression::Field { value, .. } => contains_function(value),
+        Expression::Variant { args, .. } => args.iter().any(contains_function),
+        _ => false,
+    }
+}
+
+// The names a block declares in the order the resolver indexes them.
+fn declared_names(statements: &[Statement]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for statement in statements {
+        if let Statement::VarDecl { name, .. } | Statement::Import { name, .. } = statement {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+    }
+    names
+}
+
+// Whether the statements create function literals, which might capture their locals.
+// Named functions have their own locals.
+fn contains_literal(statements: &[Statement]) -> bool {
+    statements.iter().any(|statement| match statement {
+        Statement::VarDecl { value, .. } | Statement::Return { value, .. } => {
+            value.as_ref().is_some_and(contains_function)
+        }
+        Statement::Assign { value, .. } | Statement::FieldAssign { value, .. } => {
+            contains_function(value)
+        }
+        Statement::If {
+            condition, body, ..
+        } => contains_function(condition) || contains_literal(body),
+        Statement::Match { value, arms, .. } => {
+            contains_function(value) || arms.iter().any(|arm| contains_literal(&arm.body))
+        }
+        Statement::Throw { value, .. } => contains_function(value),
+        Statement::Try {
+            body,
+            catch,
+            finally,
+            ..
+        } => {
+            contains_literal(body)
+                || catch
+                    .as_ref()
+                    .is_some_and(|catch| contains_literal(&catch.body))
+                || finally.as_deref().is_some_and(contains_literal)
+        }
+        Statement::Expression(expression) => contains_function(expression),
+        Statement::FunctionDecl(_)
+        | Statement::StructDecl(_)
+        | Statement::EnumDecl(_)
+        | Statement::Import { .. }
+        | Statement::Use { .. }
+        | Statement::DebugVar { .. } => false,
+    })
+}
@@ -0,0 +1,222 @@
+pub mod compiler;
+pub mod value;
+pub mod vm;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::ast::{EnumDecl, FunctionDecl, Pattern, Span, StructDecl};
+use crate::bytecode::value::Value;
+use crate::capabilities::Capabilities;
+use crate::errors::ErrorKind;
+use crate::functions::Builtin;
+use crate::variables::{Variable, VariableTypes};
+
+// A variable that function literals can capture, None until it is declared.
+pub type VariableCell = Rc<RefCell<Option<Variable>>>;
+
+// The operations of the virtual machine, values are passed around on its stack.
+// Jumps are absolute indexes into the code of the chunk.
+#[derive(Debug, Clone, Copy)]
+pub enum Instruction {
+    Constant(u32),
+    Pop,
+    GetLocal(u32),
+    SetLocal(u32),
+    GetGlobal(u32),
+    SetGlobal(u32),
+    DefineGlobal {
+        slot: u32,
+        constant: bool,
+        annotation: Option<VariableTypes>,
+    },
+    // Panics if the value on top of the stack doesn't have the type, the name is for the message.
+    CheckType {
+        expected: VariableTypes,
+        name: u32,
+    },
+    JumpUnlessTrue(u32),
+    Jump(u32),
+    // Tests the pattern with that index in the chunk against the value on top of the stack.
+    // When it matches the value is popped and the bindings are stored, otherwise it jumps to
+    // the next arm.
+    Match {
+        pattern: u32,
+        next: u32,
+    },
+    // Panics because no arm matched the value on top of the stack.
+    NoMatch,
+    // Jumps over the default value of a parameter when the call gave it an argument.
+    SkipDefault {
+        param: u32,
+        target: u32,
+    },
+    // Panics if the value on top of the stack doesn't have the type of the parameter.
+    CheckArgument(u32),
+    // The locals of functions with function literals in them are kept in cells, the names are
+    // for the messages.
+    NewCell(u32),
+    GetCell {
+        slot: u32,
+        name: u32,
+    },
+    SetCell {
+        slot: u32,
+        name: u32,
+    },
+    DefineCell {
+        slot: u32,
+        name: u32,
+        constant: bool,
+        annotation: Option<VariableTypes>,
+    },
+    GetUpvalue {
+        index: u32,
+        name: u32,
+    },
+    SetUpvalue {
+        index: u32,
+        name: u32,
+    },
+    MakeClosure(u32),
+    // Makes a list of that many values from the top of the stack.
+    MakeList(u32),
+    // Makes a struct from the values of the struct literal with that index in the chunk.
+    MakeStruct(u32),
+    GetField(u32),
+    // Makes a variant from the values on top of the stack, its enum is in the chunk.
+    MakeVariant(u32),
+    // Sets the field at the end of the path in a copy of the struct on top of the stack, the
+    // value is below it.
+    SetField(u32),
+    DeclareFunction {
+        entry: u32,
+        function: u32,
+    },
+    // The values of the keyword arguments come after the positional ones, their names are in
+    // the keyword names of the chunk.
+    Call {
+        entry: u32,
+        args: u32,
+        keywords: Option<u32>,
+    },
+    // Calls add, sub or eq without making strings of the numbers and booleans, the builtin of
+    // the entry gets the other values.
+    Operator {
+        operator: Operator,
+        entry: u32,
+        args: u32,
+    },
+    // Calls the function in the value below the arguments.
+    CallValue {
+        args: u32,
+        keywords: Option<u32>,
+        name: u32,
+    },
+    Return,
+    // An error until the matching EndTry jumps to the target with the error on the stack,
+    // after the stack and the frames are back to how they were here.
+    Try(u32),
+    EndTry,
+    // Raises the value on top of the stack as an error.
+    Throw,
+    // Pushes the module of the file with that index, the file runs the first time.
+    Import(u32),
+    DebugVar(u32),
+    // Raises an error with the message from the names of the chunk.
+    Fail {
+        kind: ErrorKind,
+        message: u32,
+    },
+}
+
+// The builtins the VM runs itself.
+#[derive(Debug, Clone, Copy)]
+pub enum Operator {
+    Add,
+    Sub,
+    Eq,
+}
+
+// The code of a single function, or of the whole script.
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<Instruction>,
+    pub spans: Vec<Span>, // Of the statement every instruction belongs to, for the errors.
+    pub constants: Vec<Value>,
+    pub names: Vec<String>,
+    pub functions: Vec<Rc<CompiledFunction>>,
+    pub keyword_names: Vec<Vec<String>>,
+    pub struct_literals: Vec<(Rc<StructDecl>, Vec<String>)>, // With the fields in written order.
+    pub field_paths: Vec<Vec<String>>,
+    pub variants: Vec<(Rc<EnumDecl>, String, usize)>, // With the number of values they get.
+    pub patterns: Vec<(Pattern, Vec<u32>)>,           // With the slots of their bindings.
+    pub debug_scopes: Vec<Vec<DebugVariable>>,        // The variables debug.var prints.
+}
+
+#[derive(Debug)]
+pub struct CompiledFunction {
+    pub decl: Option<Rc<FunctionDecl>>, // None for the script itself.
+    pub module: usize,                  // The index of the file it is in.
+    pub chunk: Chunk,
+    pub slot_count: usize,
+    pub boxed: bool, // Its locals are cells.
+    pub captures: Vec<Capture>,
+}
+
+// Where a function literal gets a captured variable from when it is created.
+#[derive(Debug, Clone, Copy)]
+pub enum Capture {
+    Cell(u32),
+    Upvalue(u32),
+}
+
+// A variable in the scope of a debug.var statement.
+#[derive(Debug, Clone)]
+pub struct DebugVariable {
+    pub name: String,
+    pub location: SlotLocation,
+    pub constant: bool,
+    pub annotation: Option<VariableTypes>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlotLocation {
+    Local(u32),
+    Global(u32),
+}
+
+// Every function name used in a file gets an entry, the VM fills in the user functions while
+// running and falls back to the builtin, like the FunctionContainer does.
+#[derive(Debug, Clone)]
+pub struct FunctionEntry {
+    pub name: String,
+    pub module: usize,
+    pub builtin: Option<Builtin>,
+}
+
+// A file of the program, the files share the globals and the entries of the program but each
+// uses its own part of them.
+#[derive(Debug)]
+pub struct CompiledModule {
+    pub path: String,
+    pub script: Rc<CompiledFunction>,
+    pub exports: Vec<(String, ExportSlot)>,
+    pub uses: Vec<String>, // For the names of builtins called through their name as a string.
+}
+
+// Where the VM finds what a file exports after running it.
+#[derive(Debug, Clone, Copy)]
+pub enum ExportSlot {
+    Function(u32), // The entry of the function.
+    Constant(u32), // The slot of the global.
+}
+
+// The first module is the file that is run.
+#[derive(Debug)]
+pub struct Program {
+    pub modules: Vec<Rc<CompiledModule>>,
+    pub global_names: Vec<String>,
+    pub function_entries: Vec<FunctionEntry>,
+    pub capabilities: Capabilities, // The ones it was compiled with, the VM looks up names with them.
+}
@@ -0,0 +1,147 @@
+use crate::bytecode::Operator;
+use crate::limits::size;
+use crate::variables::{VariableContent, VariableTypes};
+
+// A value on the stack or in the locals of the VM. Numbers and booleans are kept as they are,
+// so adding, comparing and jumping don't parse strings. They stand for the VariableContent
+// with the number written the way Rust writes it, numbers written another way like the
+// literal 2.50 stay in a VariableContent so they still print the way they were written.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Null,
+    Content(VariableContent),
+}
+
+impl Value {
+    // Literals are read once when they are compiled, so their numbers are typed when they can be.
+    pub fn constant(content: VariableContent) -> Value {
+        match content.data_type {
+            VariableTypes::FLOAT => match content.value.parse::<f64>() {
+                Ok(float) if float.to_string() == content.value => Value::Float(float),
+                _ => Value::Content(content),
+            },
+            _ => Value::from_content(content),
+        }
+    }
+
+    // Checking a FLOAT would take writing it again, so only INTs are typed here.
+    pub fn from_content(content: VariableContent) -> Value {
+        match content.data_type {
+            VariableTypes::INT => match written_int(&content.value) {
+                Some(int) => Value::Int(int),
+                None => Value::Content(content),
+            },
+            VariableTypes::BOOL if content.value == "true" => Value::Bool(true),
+            VariableTypes::BOOL if content.value == "false" => Value::Bool(false),
+            VariableTypes::NULL if content.value.is_empty() => Value::Null,
+            _ => Value::Content(content),
+        }
+    }
+
+    pub fn into_content(self) -> VariableContent {
+        match self {
+            Value::Int(int) => VariableContent::new(int.to_string(), VariableTypes::INT),
+            Value::Float(float) => VariableContent::new(float.to_string(), VariableTypes::FLOAT),
+            Value::Bool(bool) => VariableContent::new(bool.to_string(), VariableTypes::BOOL),
+            Value::Null => VariableContent::null(),
+            Value::Content(content) => content,
+        }
+    }
+
+    pub fn to_content(&self) -> VariableContent {
+        self.clone().into_content()
+    }
+
+    pub fn data_type(&self) -> VariableTypes {
+        match self {
+            Value::Int(_) => VariableTypes::INT,
+            Value::Float(_) => VariableTypes::FLOAT,
+            Value::Bool(_) => VariableTypes::BOOL,
+            Value::Null => VariableTypes::NULL,
+            Value::Content(content) => content.data_type,
+        }
+    }
+
+    // The same as limits::size of its VariableContent.
+    pub fn size(&self) -> usize {
+        match self {
+            Value::Content(content) => size(content),
+            _ => size(&self.to_content()),
+        }
+    }
+
+    // The number add and sub read, None for values that aren't numbers.
+    pub fn number(&self) -> Option<f64> {
+        match self {
+            Value::Int(int) => Some(*int as f64),
+            Value::Float(float) => Some(*float),
+            Value::Content(content)
+                if matches!(content.data_type, VariableTypes::INT | VariableTypes::FLOAT) =>
+            {
+                content.value.parse().ok()
+            }
+            _ => None,
+        }
+    }
+
+    // Like functions::values_equal, None when one of them isn't typed.
+    pub fn typed_equal(&self, other: &Value) -> Option<bool> {
+        let equal = match (self, other) {
+            (Value::Int(a), Value::Int(b)) => a == b,
+            // Rust writes every FLOAT differently, but NaN always the same and -0 apart from 0.
+            (Value::Float(a), Value::Float(b)) => {
+                (a == b && a.is_sign_negative() == b.is_sign_negative())
+                    || (a.is_nan() && b.is_nan())
+            }
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Null, Value::Null) => true,
+            (Value::Content(_), _) | (_, Value::Content(_)) => return None,
+            _ => false,
+        };
+        Some(equal)
+    }
+}
+
+// What add, sub and eq give for typed values, None leaves the call to the builtin so its
+// errors stay the same.
+pub fn operate(operator: Operator, args: &[Value]) -> Option<Value> {
+    let (first, rest) = args.split_first()?;
+    if rest.is_empty() {
+        return None;
+    }
+    match operator {
+        Operator::Add => {
+            let mut sum = first.number()?;
+            for arg in rest {
+                sum += arg.number()?;
+            }
+            Some(Value::Float(sum))
+        }
+        Operator::Sub => {
+            let mut sum = first.number()?;
+            for arg in rest {
+                sum -= arg.number()?;
+            }
+            Some(Value::Float(sum))
+        }
+        Operator::Eq => {
+            let mut equal = true;
+            for arg in rest {
+                equal &= first.typed_equal(arg)?;
+            }
+            Some(Value::Bool(equal))
+        }
+    }
+}
+
+// The INT when the text is how Rust writes it, without a plus or leading zeros.
+fn written_int(text: &str) -> Option<i64> {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    if digits.starts_with('+') || (digits.starts_with('0') && text != "0") {
+        return None;
+    }
+    text.parse().ok()
+}
@@ -0,0 +1,747 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::ast::StructDecl;
+use crate::bytecode::value::{operate, Value};
+use crate::bytecode::{
+    Capture, CompiledFunction, CompiledModule, ExportSlot, FunctionEntry, Instruction, Program,
+    SlotLocation, VariableCell,
+};
+use crate::clock::Clock;
+use crate::enums::{build_variant, match_pattern, no_match};
+use crate::errors::{
+    catch_error, error_decl, error_value, raise, raise_error, thrown_error, ErrorKind, ScriptError,
+};
+use crate::functions::{
+    bind_arguments, check_argument, check_debug_var, check_return, denied, Builtin,
+    FunctionContainer,
+};
+use crate::host::Host;
+use crate::limits::{size, Budget, Limits};
+use crate::structs::{build_struct, get_field, set_field};
+use crate::variables::{check_annotation, FunctionValue, Variable, VariableContent, VariableTypes};
+
+// Runs compiled programs, it raises the same errors as the CodeExecutor with the same messages.
+pub struct VirtualMachine {
+    globals: Vec<Option<Variable>>,
+    global_names: Vec<String>,
+    functions: Vec<Option<Rc<CompiledFunction>>>, // The user functions declared so far.
+    function_entries: Vec<FunctionEntry>,
+    entry_names: HashMap<(usize, String), usize>, // By the file and the name of the function.
+    modules: Vec<Rc<CompiledModule>>,
+    imported: Vec<Option<VariableContent>>, // The modules of the files that ran already.
+    builtins: FunctionContainer,            // For the functions only named in strings.
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    handlers: Vec<Handler>,
+    thrown: Option<VariableContent>, // The value of the error being raised by a throw.
+    error_decl: Rc<StructDecl>,
+    budget: Budget, // Its scopes are left out, the VM has none while running.
+    host: Host,
+}
+
+// Where a Try continues after an error, with the heights to cut the stack and frames back to.
+struct Handler {
+    frames: usize,
+    stack: usize,
+    target: usize,
+}
+
+// A function being run, its locals are indexed by the slots the compiler handed out.
+struct Frame {
+    function: Rc<CompiledFunction>,
+    ip: usize,
+    locals: Vec<Value>,
+    cells: Vec<VariableCell>, // Instead of the locals when the function is boxed.
+    upvalues: Rc<Vec<VariableCell>>,
+    defaulted: Vec<bool>, // The parameters that didn't get an argument.
+    held: usize,          // The bytes of the frames below, only counted with a memory limit.
+}
+
+impl Frame {
+    fn new(
+        function: Rc<CompiledFunction>,
+        mut locals: Vec<Value>,
+        upvalues: Rc<Vec<VariableCell>>,
+    ) -> Frame {
+        locals.resize(function.slot_count, Value::Null);
+        let cells = match function.boxed {
+            true => (0..function.slot_count).map(|_| new_cell()).collect(),
+            false => Vec::new(),
+        };
+        Frame {
+            function,
+            ip: 0,
+            locals,
+            cells,
+            upvalues,
+            defaulted: Vec::new(),
+            held: 0,
+        }
+    }
+
+    // Matches the arguments to the parameters of a user function, the parameters left for
+    // their default values are NULL until the function evaluates those.
+    fn bind(
+        function: Rc<CompiledFunction>,
+        args: Vec<Value>,
+        keywords: Vec<(String, Value)>,
+        upvalues: Rc<Vec<VariableCell>>,
+    ) -> Frame {
+        let decl = function.decl.as_ref().unwrap();
+        // Calls that give every parameter a positional argument keep their values as they are.
+        if keywords.is_empty() && decl.rest.is_none() && args.len() == decl.params.len() {
+            for (param, arg) in decl.params.iter().zip(&args) {
+                check_argument(decl, param, arg.data_type());
+            }
+            let defaulted = vec![false; args.len()];
+            let mut frame = Frame::new(function, args, upvalues);
+            frame.defaulted = defaulted;
+            return frame;
+        }
+
+        let args = args.into_iter().map(Value::into_content).collect();
+        let keywords = keywords
+            .into_iter()
+            .map(|(name, value)| (name, value.into_content()))
+            .collect();
+        let (params, rest) = bind_arguments(decl, args, keywords);
+        let defaulted = params.iter().map(Option::is_none).collect();
+        let mut locals: Vec<Value> = params
+            .into_iter()
+            .map(|arg| arg.map_or(Value::Null, Value::from_content))
+            .collect();
+        locals.extend(rest.map(Value::from_content));
+        let mut frame = Frame::new(function, locals, upvalues);
+        frame.defaulted = defaulted;
+        frame
+    }
+
+    // The bytes of its variables, see limits::size.
+    fn size(&self) -> usize {
+        let locals: usize = self.locals.iter().map(Value::size).sum();
+        let cells: usize = self
+            .cells
+            .iter()
+            .filter_map(|cell| cell.borrow().as_ref().map(|var| size(&var.content)))
+            .sum();
+        locals + cells
+    }
+
+    fn name(&self, name: u32) -> &str {
+        &self.function.chunk.names[name as usize]
+    }
+}
+
+fn new_cell() -> VariableCell {
+    Rc::new(RefCell::new(None))
+}
+
+// Globals and cells follow the same rules as the variables of the CodeExecutor.
+fn get_variable(variable: &Option<Variable>, name: &str) -> VariableContent {
+    match variable {
+        Some(variable) => variable.content.clone(),
+        None => raise(
+            ErrorKind::UnknownVariable,
+            format!("Couldn't get variable {}.", name),
+        ),
+    }
+}
+
+fn set_variable(variable: &mut Option<Variable>, name: &str, value: VariableContent) {
+    match variable {
+        Some(variable) => {
+            if variable.constant {
+                raise(
+                    ErrorKind::Constant,
+                    format!("Couldn't set {} as it is a constant.", name),
+                );
+            }
+            variable.check_type(&value);
+            variable.content = value;
+        }
+        None => raise(
+            ErrorKind::UnknownVariable,
+            format!("Couldn't set variable {} as it doesn't exist.", name),
+        ),
+    }
+}
+
+fn define_variable(existing: &mut Option<Variable>, variable: Variable) {
+    if existing.as_ref().is_some_and(|existing| existing.constant) {
+        let message = format!(
+            "Couldn't declare {} as it is already a constant.",
+            variable.name
+        );
+        raise(ErrorKind::Constant, message);
+    }
+    variable.check_type(&variable.content);
+    *existing = Some(variable);
+}
+
+// For the code that works on VariableContents, like the builtins.
+fn pop_content(stack: &mut Vec<Value>) -> VariableContent {
+    let value = stack.pop().expect("The stack of the VM is empty.");
+    value.into_content()
+}
+
+fn pop_contents(stack: &mut Vec<Value>, count: usize) -> Vec<VariableContent> {
+    let values = stack.split_off(stack.len() - count);
+    values.into_iter().map(Value::into_content).collect()
+}
+
+// Takes the values of the keyword arguments from the top of the stack, with their names.
+fn keyword_arguments(
+    stack: &mut Vec<Value>,
+    function: &CompiledFunction,
+    keywords: Option<u32>,
+) -> Vec<(String, Value)> {
+    let names = match keywords {
+        Some(keywords) => &function.chunk.keyword_names[keywords as usize],
+        None => return Vec::new(),
+    };
+    let values = stack.split_off(stack.len() - names.len());
+    names.iter().cloned().zip(values).collect()
+}
+
+impl VirtualMachine {
+    pub fn new(program: &Program) -> VirtualMachine {
+        VirtualMachine {
+            globals: program.global_names.iter().map(|_| None).collect(),
+            global_names: program.global_names.clone(),
+            functions: program.function_entries.iter().map(|_| None).collect(),
+            function_entries: program.function_entries.clone(),
+            entry_names: program
+                .function_entries
+                .iter()
+                .enumerate()
+                .map(|(i, entry)| ((entry.module, entry.name.clone()), i))
+                .collect(),
+            modules: program.modules.clone(),
+            imported: program.modules.iter().map(|_| None).collect(),
+            builtins: FunctionContainer::with_capabilities(program.capabilities.clone()),
+            stack: Vec::new(),
+            frames: Vec::new(),
+            handlers: Vec::new(),
+            thrown: None,
+            error_decl: error_decl(),
+            budget: Budget::new(Limits::new()),
+            host: Host::new(),
+        }
+    }
+
+    pub fn set_host(&mut self, host: Host) {
+        self.host = host;
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.budget = Budget::new(limits);
+    }
+
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.host.clock = clock;
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("The stack of the VM is empty.")
+    }
+
+    // Runs the first file, giving back the error that no try statement caught.
+    pub fn run(&mut self) -> Result<(), ScriptError> {
+        let script = self.modules[0].script.clone();
+        self.budget.start(&*self.host.clock);
+        self.host.deadline = self.budget.deadline();
+        self.frames
+            .push(Frame::new(script, Vec::new(), Rc::new(Vec::new())));
+        catch_error(|| self.execute(0)).map(|_| ())
+    }
+
+    // Runs the file the first time it is imported, every import gets the same module.
+    fn import(&mut self, module: usize) -> VariableContent {
+        if let Some(imported) = &self.imported[module] {
+            return imported.clone();
+        }
+        let file = self.modules[module].clone();
+        let frame = Frame::new(file.script.clone(), Vec::new(), Rc::new(Vec::new()));
+        self.call_frame(frame);
+
+        let exports = file
+            .exports
+            .iter()
+            .map(|(name, export)| {
+                let value = match *export {
+                    ExportSlot::Function(entry) => {
+                        let function = self.functions[entry as usize].clone().unwrap();
+                        let params = function.decl.as_ref().unwrap().param_names();
+                        let upvalues = Vec::new();
+                        let value = FunctionValue::Compiled { function, upvalues };
+                        VariableContent::function(value, &params)
+                    }
+                    ExportSlot::Constant(slot) => get_variable(&self.globals[slot as usize], name),
+                };
+                (name.clone(), value)
+            })
+            .collect();
+        let imported = VariableContent::module(&file.path, exports);
+        self.imported[module] = Some(imported.clone());
+        imported
+    }
+
+    // Runs a function to its end and gives back what it returned.
+    fn call_frame(&mut self, frame: Frame) -> Value {
+        let base = self.frames.len();
+        self.push_frame(frame);
+        self.execute(base)
+    }
+
+    // The frames below it are the script and the calls and imports running.
+    fn push_frame(&mut self, mut frame: Frame) {
+        self.budget.check_depth(self.frames.len() - 1);
+        if self.budget.counts_memory() {
+            let below = self.frames.last().unwrap();
+            frame.held = below.held + below.size();
+        }
+        self.frames.push(frame);
+    }
+
+    // Calls the user function declared with the name of the entry, or else its builtin.
+    fn call_entry(&mut self, entry: usize, args: Vec<Value>, keywords: Vec<(String, Value)>) {
+        if let Some(function) = &self.functions[entry] {
+            let upvalues = Rc::new(Vec::new());
+            let frame = Frame::bind(function.clone(), args, keywords, upvalues);
+            self.push_frame(frame);
+        } else if let Some(builtin) = self.function_entries[entry].builtin {
+            let name = &self.function_entries[entry].name;
+            if !keywords.is_empty() {
+                let message = format!("Function {} doesn't take keyword arguments.", name);
+                raise(ErrorKind::Argument, message);
+            }
+            if let Builtin::Denied(capability) = builtin {
+                denied(name, capability);
+            }
+            let args = args.into_iter().map(Value::into_content).collect();
+            let result = self.call_builtin(builtin, args);
+            self.stack.push(Value::from_content(result));
+        } else {
+            let message = format!("Unknown function '{}'.", self.function_entries[entry].name);
+            raise(ErrorKind::UnknownFunction, message);
+        }
+    }
+
+    // Higher order builtins call back into the VM to run the functions they are given.
+    fn call_builtin(&mut self, builtin: Builtin, args: Vec<VariableContent>) -> VariableContent {
+        match builtin {
+            Builtin::Simple(function) => function(args),
+            Builtin::HigherOrder(function) => {
+                function(args, &mut |callee, args| self.call_value(callee, args))
+            }
+            Builtin::Host(function) => {
+                let result = function(args, &mut self.host);
+                self.budget.check_time(&*self.host.clock);
+                result
+            }
+            Builtin::Denied(_) => unreachable!("Denied builtins are refused by their callers."),
+        }
+    }
+
+    // Calls a function value, a string calls the function with that name.
+    fn call_value(
+        &mut self,
+        callee: &VariableContent,
+        args: Vec<VariableContent>,
+    ) -> VariableContent {
+        match callee.as_function() {
+            Some(FunctionValue::Compiled { function, upvalues }) => {
+                let upvalues = Rc::new(upvalues.clone());
+                let args = args.into_iter().map(Value::from_content).collect();
+                let frame = Frame::bind(function.clone(), args, Vec::new(), upvalues);
+                self.call_frame(frame).into_content()
+            }
+            // The function is looked up in the file of the code calling the builtin.
+            _ if callee.data_type == VariableTypes::STRING => {
+                let module = self.frames.last().unwrap().function.module;
+                let key = (module, callee.value.clone());
+                let entry = self.entry_names.get(&key).copied();
+                let function = entry.and_then(|entry| self.functions[entry].clone());
+                if let Some(function) = function {
+                    let args = args.into_iter().map(Value::from_content).collect();
+                    let frame = Frame::bind(function, args, Vec::new(), Rc::new(Vec::new()));
+                    return self.call_frame(frame).into_content();
+                }
+                let uses = &self.modules[module].uses;
+                let builtin = self
+                    .builtins
+                    .builtin_name(&callee.value, uses)
+                    .and_then(|name| self.builtins.get_builtin(&name));
+                match builtin {
+                    Some(Builtin::Denied(capability)) => denied(&callee.value, capability),
+                    Some(builtin) => self.call_builtin(builtin, args),
+                    None => raise(
+                        ErrorKind::UnknownFunction,
+                        format!("Unknown function '{}'.", callee.value),
+                    ),
+                }
+            }
+            _ => raise(
+                ErrorKind::Type,
+                format!(
+                    "Expected a function or the name of one, got {:?}.",
+                    callee.data_type
+                ),
+            ),
+        }
+    }
+
+    // Returns when the frame at the base returns. Errors go to the Try statements run by this
+    // call, the others are left to the calls further out.
+    fn execute(&mut self, base: usize) -> Value {
+        let outer_handlers = self.handlers.len();
+        loop {
+            let error = match catch_error(|| self.execute_code(base)) {
+                Ok(result) => return result,
+                Err(error) => error,
+            };
+            let frame = self.frames.last().unwrap();
+            let file = &self.modules[frame.function.module].path;
+            let error = error.at(file, frame.function.chunk.spans[frame.ip - 1]);
+            if self.handlers.len() == outer_handlers {
+                raise_error(error);
+            }
+
+            let handler = self.handlers.pop().unwrap();
+            self.frames.truncate(handler.frames);
+            self.stack.truncate(handler.stack);
+            self.frames.last_mut().unwrap().ip = handler.target;
+            let value = self.thrown.take().unwrap_or_else(VariableContent::null);
+            let error = error_value(&error, value, &self.error_decl);
+            self.stack.push(Value::Content(error));
+        }
+    }
+
+    fn execute_code(&mut self, base: usize) -> Value {
+        loop {
+            let frame = self.frames.last_mut().unwrap();
+            let instruction = frame.function.chunk.code[frame.ip];
+            frame.ip += 1;
+            // The value on top is the one the instruction before made.
+            self.budget.step(&*self.host.clock);
+            if self.budget.counts_memory() {
+                match self.stack.last() {
+                    Some(Value::Content(value)) => self.budget.check_size(value),
+                    Some(value) => self.budget.check_size(&value.to_content()),
+                    None => {}
+                }
+                let globals: usize = self
+                    .globals
+                    .iter()
+                    .flatten()
+                    .map(|var| size(&var.content))
+                    .sum();
+                self.budget
+                    .count_memory(frame.held + frame.size() + globals);
+            }
+
+            match instruction {
+                Instruction::Constant(index) => {
+                    let value = frame.function.chunk.constants[index as usize].clone();
+                    self.stack.push(value);
+                }
+                Instruction::Pop => {
+                    self.pop();
+                }
+                Instruction::GetLocal(slot) => {
+                    let value = frame.locals[slot as usize].clone();
+                    self.stack.push(value);
+                }
+                Instruction::SetLocal(slot) => {
+                    let value = self.stack.pop().expect("The stack of the VM is empty.");
+                    frame.locals[slot as usize] = value;
+                }
+                Instruction::GetGlobal(slot) => {
+                    let name = &self.global_names[slot as usize];
+                    let value = get_variable(&self.globals[slot as usize], name);
+                    self.stack.push(Value::from_content(value));
+                }
+                Instruction::SetGlobal(slot) => {
+                    let value = self.pop().into_content();
+                    let name = &self.global_names[slot as usize];
+                    set_variable(&mut self.globals[slot as usize], name, value);
+                }
+                Instruction::DefineGlobal {
+                    slot,
+                    constant,
+                    annotation,
+                } => {
+                    let variable = Variable {
+                        name: self.global_names[slot as usize].clone(),
+                        content: self.pop().into_content(),
+                        constant,
+                        annotation,
+                    };
+                    define_variable(&mut self.globals[slot as usize], variable);
+                }
+                Instruction::NewCell(slot) => frame.cells[slot as usize] = new_cell(),
+                Instruction::GetCell { slot, name } => {
+                    let cell = frame.cells[slot as usize].borrow();
+                    let value = get_variable(&cell, frame.name(name));
+                    self.stack.push(Value::from_content(value));
+                }
+                Instruction::SetCell { slot, name } => {
+                    let value = self.stack.pop().expect("The stack of the VM is empty.");
+                    let value = value.into_content();
+                    let mut cell = frame.cells[slot as usize].borrow_mut();
+                    set_variable(&mut cell, frame.name(name), value);
+                }
+                Instruction::DefineCell {
+                    slot,
+                    name,
+                    constant,
+                    annotation,
+                } => {
+                    let variable = Variable {
+                        name: frame.name(name).to_string(),
+                        content: pop_content(&mut self.stack),
+                        constant,
+                        annotation,
+                    };
+                    define_variable(&mut frame.cells[slot as usize].borrow_mut(), variable);
+                }
+                Instruction::GetUpvalue { index, name } => {
+                    let cell = frame.upvalues[index as usize].borrow();
+                    let value = get_variable(&cell, frame.name(name));
+                    self.stack.push(Value::from_content(value));
+                }
+                Instruction::SetUpvalue { index, name } => {
+                    let value = self.stack.pop().expect("The stack of the VM is empty.");
+                    let value = value.into_content();
+                    let mut cell = frame.upvalues[index as usize].borrow_mut();
+                    set_variable(&mut cell, frame.name(name), value);
+                }
+                // Function literals capture the cells of the variables they use.
+                Instruction::MakeList(count) => {
+                    let items = pop_contents(&mut self.stack, count as usize);
+                    self.stack
+                        .push(Value::Content(VariableContent::list(items)));
+                }
+                Instruction::MakeStruct(index) => {
+                    let (decl, names) = &frame.function.chunk.struct_literals[index as usize];
+                    let values = pop_contents(&mut self.stack, names.len());
+                    let fields = names.iter().cloned().zip(values).collect();
+                    self.stack.push(Value::Content(build_struct(decl, fields)));
+                }
+                Instruction::MakeVariant(index) => {
+                    let (decl, variant, count) = &frame.function.chunk.variants[index as usize];
+                    let payload = pop_contents(&mut self.stack, *count);
+                    self.stack
+                        .push(Value::Content(build_variant(decl, variant, payload)));
+                }
+                Instruction::GetField(name) => {
+                    let value = pop_content(&mut self.stack);
+                    let field = get_field(&value, frame.name(name));
+                    self.stack.push(Value::from_content(field));
+                }
+                Instruction::SetField(path) => {
+                    let record = pop_content(&mut self.stack);
+                    let value = pop_content(&mut self.stack);
+                    let path = &frame.function.chunk.field_paths[path as usize];
+                    self.stack
+                        .push(Value::Content(set_field(&record, path, value)));
+                }
+                Instruction::MakeClosure(function) => {
+                    let function = frame.function.chunk.functions[function as usize].clone();
+                    let upvalues = function
+                        .captures
+                        .iter()
+                        .map(|capture| match capture {
+                            Capture::Cell(slot) => frame.cells[*slot as usize].clone(),
+                            Capture::Upvalue(index) => frame.upvalues[*index as usize].clone(),
+                        })
+                        .collect();
+                    let params = function.decl.as_ref().unwrap().param_names();
+                    let value = FunctionValue::Compiled { function, upvalues };
+                    let value = VariableContent::function(value, &params);
+                    self.stack.push(Value::Content(value));
+                }
+                Instruction::CheckType { expected, name } => {
+                    let name = &frame.function.chunk.names[name as usize];
+                    let data_type = self.stack.last().unwrap().data_type();
+                    check_annotation(name, Some(expected), data_type);
+                }
+                Instruction::SkipDefault { param, target } => {
+                    if !frame.defaulted[param as usize] {
+                        frame.ip = target as usize;
+                    }
+                }
+                Instruction::CheckArgument(param) => {
+                    let decl = frame.function.decl.as_ref().unwrap();
+                    let param = &decl.params[param as usize];
+                    check_argument(decl, param, self.stack.last().unwrap().data_type());
+                }
+                Instruction::JumpUnlessTrue(target) => {
+                    let condition = self.stack.pop().expect("The stack of the VM is empty.");
+                    match condition {
+                        Value::Bool(true) => {}
+                        Value::Bool(false) => frame.ip = target as usize,
+                        // Only true and false are typed, other BOOLs are false like in the
+                        // CodeExecutor.
+                        condition if condition.data_type() == VariableTypes::BOOL => {
+                            frame.ip = target as usize;
+                        }
+                        condition => raise(
+                            ErrorKind::Type,
+                            format!(
+                                "Data type '{:?}' not implemented for if condition.",
+                                condition.data_type()
+                            ),
+                        ),
+                    }
+                }
+                Instruction::Jump(target) => frame.ip = target as usize,
+                Instruction::Match { pattern, next } => {
+                    let (pattern, slots) = &frame.function.chunk.patterns[pattern as usize];
+                    let mut bindings = Vec::new();
+                    let value = self.stack.last().expect("The stack of the VM is empty.");
+                    if !match_pattern(pattern, &value.to_content(), &mut bindings) {
+                        frame.ip = next as usize;
+                        continue;
+                    }
+
+                    self.stack.pop();
+                    let names = pattern.bindings();
+                    for ((slot, name), content) in slots.iter().zip(names).zip(bindings) {
+                        let slot = *slot as usize;
+                        if !frame.function.boxed {
+                            frame.locals[slot] = Value::from_content(content);
+                            continue;
+                        }
+                        let cell = new_cell();
+                        let variable = Variable {
+                            name,
+                            content,
+                            constant: false,
+                            annotation: None,
+                        };
+                        define_variable(&mut cell.borrow_mut(), variable);
+                        frame.cells[slot] = cell;
+                    }
+                }
+                Instruction::NoMatch => no_match(&pop_content(&mut self.stack)),
+                Instruction::DeclareFunction { entry, function } => {
+                    // Only the builtins outside of modules can't be declared again.
+                    let entry = entry as usize;
+                    let name = &self.function_entries[entry].name;
+                    if self.builtins.get_builtin(name).is_some() {
+                        let message =
+                            format!("Can't declare function '{}' as it is a builtin.", name);
+                        raise(ErrorKind::Constant, message);
+                    }
+                    self.functions[entry] =
+                        Some(frame.function.chunk.functions[function as usize].clone());
+                }
+                Instruction::Call {
+                    entry,
+                    args,
+                    keywords,
+                } => {
+                    let keywords = keyword_arguments(&mut self.stack, &frame.function, keywords);
+                    let args = self.stack.split_off(self.stack.len() - args as usize);
+                    self.call_entry(entry as usize, args, keywords);
+                }
+                Instruction::Operator {
+                    operator,
+                    entry,
+                    args,
+                } => {
+                    let args = self.stack.split_off(self.stack.len() - args as usize);
+                    match operate(operator, &args) {
+                        Some(result) => self.stack.push(result),
+                        None => self.call_entry(entry as usize, args, Vec::new()),
+                    }
+                }
+                Instruction::CallValue {
+                    args,
+                    keywords,
+                    name,
+                } => {
+                    let keywords = keyword_arguments(&mut self.stack, &frame.function, keywords);
+                    let args = self.stack.split_off(self.stack.len() - args as usize);
+                    let callee = pop_content(&mut self.stack);
+                    match callee.as_function() {
+                        Some(FunctionValue::Compiled { function, upvalues }) => {
+                            let upvalues = Rc::new(upvalues.clone());
+                            let frame = Frame::bind(function.clone(), args, keywords, upvalues);
+                            self.push_frame(frame);
+                        }
+                        _ => raise(
+                            ErrorKind::Type,
+                            format!(
+                                "Variable {} is of type {:?} and can't be called.",
+                                frame.name(name),
+                                callee.data_type
+                            ),
+                        ),
+                    }
+                }
+                Instruction::Return => {
+                    let result = self.pop();
+                    let frame = self.frames.pop().unwrap();
+                    if let Some(decl) = &frame.function.decl {
+                        check_return(decl, result.data_type());
+                    }
+                    if self.frames.len() == base {
+                        return result;
+                    }
+                    self.stack.push(result);
+                }
+                Instruction::DebugVar(index) => {
+                    check_debug_var(self.builtins.capabilities());
+                    for debug_variable in &frame.function.chunk.debug_scopes[index as usize] {
+                        match debug_variable.location {
+                            SlotLocation::Local(slot) if frame.function.boxed => {
+                                if let Some(variable) = &*frame.cells[slot as usize].borrow() {
+                                    self.host.write(&format!("{}\n", variable.debug_string()));
+                                }
+                            }
+                            SlotLocation::Local(slot) => {
+                                let variable = Variable {
+                                    name: debug_variable.name.clone(),
+                                    content: frame.locals[slot as usize].to_content(),
+                                    constant: debug_variable.constant,
+                                    annotation: debug_variable.annotation,
+                                };
+                                self.host.write(&format!("{}\n", variable.debug_string()));
+                            }
+                            SlotLocation::Global(slot) => {
+                                if let Some(variable) = &self.globals[slot as usize] {
+                                    self.host.write(&format!("{}\n", variable.debug_string()));
+                                }
+                            }
+                        }
+                    }
+                }
+                Instruction::Import(module) => {
+                    let imported = self.import(module as usize);
+                    self.stack.push(Value::Content(imported));
+                }
+                Instruction::Try(target) => self.handlers.push(Handler {
+                    frames: self.frames.len(),
+                    stack: self.stack.len(),
+                    target: target as usize,
+                }),
+                Instruction::EndTry => {
+                    self.handlers.pop();
+                }
+                Instruction::Throw => {
+                    let value = pop_content(&mut self.stack);
+                    let (error, value) = thrown_error(value, &self.error_decl);
+                    self.thrown = Some(value);
+                    raise_error(error);
+                }
+                Instruction::Fail { kind, message } => {
+                    raise(kind, frame.function.chunk.names[message as usize].clone());
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,91 @@
+// What a builtin touches outside of the script, a host only allows the ones it trusts the
+// script with. Builtins without a capability are always allowed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    Stdin,
+    Stdout,
+    FsRead,
+    FsWrite,
+    Env,
+    Time,
+    Random,
+}
+
+const CAPABILITIES: [Capability; 7] = [
+    Capability::Stdin,
+    Capability::Stdout,
+    Capability::FsRead,
+    Capability::FsWrite,
+    Capability::Env,
+    Capability::Time,
+    Capability::Random,
+];
+
+impl Capability {
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::Stdin => "stdin",
+            Capability::Stdout => "stdout",
+            Capability::FsRead => "fs.read",
+            Capability::FsWrite => "fs.write",
+            Capability::Env => "env",
+            Capability::Time => "time",
+            Capability::Random => "random",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Capability> {
+        CAPABILITIES
+            .iter()
+            .copied()
+            .find(|capability| capability.name() == name)
+    }
+}
+
+// The capabilities a script is allowed to use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    allowed: Vec<Capability>,
+}
+
+impl Capabilities {
+    pub fn all() -> Capabilities {
+        Capabilities {
+            allowed: CAPABILITIES.to_vec(),
+        }
+    }
+
+    // Only the builtins without side effects, like the ones of math and str.
+    pub fn none() -> Capabilities {
+        Capabilities {
+            allowed: Vec::new(),
+        }
+    }
+
+    // From names separated by commas like "stdout,fs.read".
+    pub fn from_names(names: &str) -> Result<Capabilities, String> {
+        let mut capabilities = Capabilities::none();
+        for name in names.split(',').filter(|name| !name.is_empty()) {
+            let capability = Capability::from_name(name)
+                .ok_or_else(|| format!("Unknown capability '{}'.", name))?;
+            capabilities.allow(capability);
+        }
+        Ok(capabilities)
+    }
+
+    pub fn allow(&mut self, capability: Capability) {
+        if !self.allows(capability) {
+            self.allowed.push(capability);
+        }
+    }
+
+    pub fn allows(&self, capability: Capability) -> bool {
+        self.allowed.contains(&capability)
+    }
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self::all()
+    }
+}
@@ -0,0 +1,761 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::ast::{
+    EnumDecl, Expression, FunctionDecl, KeywordArg, MatchArm, Pattern, Span, Statement,
+};
+use crate::functions::{match_arguments, FunctionContainer};
+use crate::modules::uses;
+use crate::variables::VariableTypes;
+
+// An error found in the source code before it is executed.
+#[derive(Debug)]
+pub struct CheckError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+    pub end_column: usize, // Exclusive, the end of the line if the span continues on the next one.
+    pub source_line: String,
+    pub warning: bool, // Warnings don't stop the program from running.
+}
+
+impl CheckError {
+    pub(crate) fn new(span: Span, source: &str, message: String) -> CheckError {
+        let line_start = source[..span.start].rfind('\n').map_or(0, |i| i + 1);
+        let source_line = source[line_start..].lines().next().unwrap_or("").trim_end();
+        let spanned = source[span.start..span.end].lines().next().unwrap_or("");
+        let end_column =
+            (span.column + spanned.chars().count()).min(source_line.chars().count() + 1);
+        CheckError {
+            message,
+            line: span.line,
+            column: span.column,
+            end_column: end_column.max(span.column + 1),
+            source_line: source_line.to_string(),
+            warning: false,
+        }
+    }
+
+    pub(crate) fn warning(span: Span, source: &str, message: String) -> CheckError {
+        CheckError {
+            warning: true,
+            ..CheckError::new(span, source, message)
+        }
+    }
+}
+
+impl fmt::Display for CheckError {
+    // Shows the message with the line it is about and marks the span below it.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = if self.warning { "warning: " } else { "" };
+        writeln!(f, "{}:{}: {}{}", self.line, self.column, kind, self.message)?;
+        writeln!(f, "    {}", self.source_line)?;
+        write!(
+            f,
+            "    {}{}",
+            " ".repeat(self.column - 1),
+            "^".repeat(self.end_column - self.column)
+        )
+    }
+}
+
+// Finds the mistakes that don't need the code to run, like reassigning a constant.
+// With check_types it also infers the types of values and reports every mismatch it can prove,
+// together with functions that are never declared.
+pub fn check_program(program: &[Statement], source: &str, check_types: bool) -> Vec<CheckError> {
+    let mut checker = Checker {
+        source,
+        scopes: vec![HashMap::new()],
+        return_type: None,
+        builtins: FunctionContainer::new(),
+        user_functions: HashMap::new(),
+        uses: uses(program),
+        function_bodies: Vec::new(),
+        check_types,
+        errors: Vec::new(),
+    };
+
+    // Functions can be called before the line declaring them, so they are collected first.
+    checker.collect_functions(program);
+    checker.check_statements(program);
+
+    // Function bodies run after the whole script is declared, so they see every global.
+    // Only the types of constants and annotated globals are certain inside of them.
+    for binding in checker.scopes[0].values_mut() {
+        if !binding.constant && !binding.annotated {
+            binding.data_type = None;
+        }
+    }
+    while let Some((decl, scopes)) = checker.function_bodies.pop() {
+        let scopes = scopes.unwrap_or_else(|| vec![checker.scopes[0].clone()]);
+        checker.check_function_body(&decl, scopes);
+    }
+
+    checker
+        .errors
+        .sort_by_key(|error| (error.line, error.column));
+    checker.errors
+}
+
+// What the checker knows about a variable.
+#[derive(Clone)]
+struct Binding {
+    constant: bool,
+    annotated: bool,
+    data_type: Option<VariableTypes>, // None when it can't be known without running the code.
+}
+
+type CheckerScope = HashMap<String, Binding>;
+
+struct Checker<'a> {
+    source: &'a str,
+    scopes: Vec<CheckerScope>,
+    return_type: Option<Option<VariableTypes>>, // Set inside of a function, to its return type.
+    builtins: FunctionContainer,
+    user_functions: HashMap<String, Rc<FunctionDecl>>,
+    uses: Vec<String>, // The builtin modules of the use statements.
+    // Checked once all globals are known, function literals keep the scopes they capture.
+    function_bodies: Vec<(Rc<FunctionDecl>, Option<Vec<CheckerScope>>)>,
+    check_types: bool,
+    errors: Vec<CheckError>,
+}
+
+impl Checker<'_> {
+    fn error(&mut self, span: Span, message: String) {
+        self.errors
+            .push(CheckError::new(span, self.source, message));
+    }
+
+    // Only reported when checking types, the interpreter finds these while running.
+    fn type_error(&mut self, span: Span, message: String) {
+        if self.check_types {
+            self.error(span, message);
+        }
+    }
+
+    fn collect_functions(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            match statement {
+                Statement::FunctionDecl(decl) => {
+                    self.user_functions.insert(decl.name.clone(), decl.clone());
+                    self.collect_functions(&decl.body);
+                }
+                Statement::If { body, .. } => self.collect_functions(body),
+                Statement::Try {
+                    body,
+                    catch,
+                    finally,
+                    ..
+                } => {
+                    self.collect_functions(body);
+                    if let Some(catch) = catch {
+                        self.collect_functions(&catch.body);
+                    }
+                    if let Some(finally) = finally {
+                        self.collect_functions(finally);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn check_statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.check_statement(statement);
+        }
+    }
+
+    // Also gives how many scopes up the binding is, 0 being the current one.
+    fn find_binding(&mut self, name: &str) -> Option<(usize, &mut Binding)> {
+        let depth = self.scopes.len();
+        self.scopes
+            .iter_mut()
+            .enumerate()
+            .rev()
+            .find_map(|(i, scope)| scope.get_mut(name).map(|b| (depth - 1 - i, b)))
+    }
+
+    fn check_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::VarDecl {
+                name,
+                annotation,
+                constant,
+                value,
+                span,
+                ..
+            } => {
+                let data_type = match value {
+                    Some(value) => self.infer(value),
+                    None => Some(VariableTypes::NULL),
+                };
+
+                if let (Some(expected), Some(actual)) = (annotation, data_type) {
+                    if *expected != actual {
+                        self.type_error(
+                            *span,
+                            format!(
+                                "Variable '{}' is of type {:?}, can't assign a value of type {:?}.",
+                                name, expected, actual
+                            ),
+                        );
+                    }
+                }
+
+                let scope = self.scopes.last_mut().unwrap();
+                if scope.get(name).is_some_and(|binding| binding.constant) {
+                    self.error(*span, format!("Can't redeclare constant '{}'.", name));
+                } else {
+                    let binding = Binding {
+                        constant: *constant,
+                        annotated: annotation.is_some(),
+                        data_type: annotation.or(data_type),
+                    };
+                    scope.insert(name.clone(), binding);
+                }
+            }
+            Statement::Assign {
+                name, value, span, ..
+            } => {
+                let data_type = self.infer(value);
+
+                // The bool tells if it should be reported without checking types.
+                let message = match self.find_binding(name) {
+                    // The resolver reports unknown variables.
+                    None => None,
+                    Some((_, binding)) if binding.constant => {
+                        Some((true, format!("Can't reassign constant '{}'.", name)))
+                    }
+                    Some((_, binding)) if binding.annotated => match (binding.data_type, data_type)
+                    {
+                        (Some(expected), Some(actual)) if expected != actual => Some((
+                            false,
+                            format!(
+                                "Variable '{}' is of type {:?}, can't assign a value of type {:?}.",
+                                name, expected, actual
+                            ),
+                        )),
+                        _ => None,
+                    },
+                    // Without an annotation the type simply changes, unless it happens in a block
+                    // that might not run.
+                    Some((depth, binding)) => {
+                        if depth == 0 || binding.data_type == data_type {
+                            binding.data_type = data_type;
+                        } else {
+                            binding.data_type = None;
+                        }
+                        None
+                    }
+                };
+                match message {
+                    Some((true, message)) => self.error(*span, message),
+                    Some((false, message)) => self.type_error(*span, message),
+                    None => {}
+                }
+            }
+            Statement::If {
+                condition, body, ..
+            } => {
+                if let Some(data_type) = self.infer(condition) {
+                    if data_type != VariableTypes::BOOL {
+                        self.type_error(
+                            condition.span(),
+                            format!(
+                                "The condition of an if should be of type BOOL, got {:?}.",
+                                data_type
+                            ),
+                        );
+                    }
+                }
+
+                self.scopes.push(HashMap::new());
+                self.check_statements(body);
+                self.scopes.pop();
+            }
+            Statement::FieldAssign {
+                name, value, span, ..
+            } => {
+                self.infer(value);
+                match self.find_binding(name) {
+                    Some((_, binding)) if binding.constant => {
+                        let message = format!("Can't set a field of constant '{}'.", name);
+                        self.error(*span, message);
+                    }
+                    Some((_, binding)) => {
+                        if let Some(data_type) = binding.data_type {
+                            self.check_has_fields(data_type, *span);
+                        }
+                    }
+                    None => {}
+                }
+            }
+            Statement::FunctionDecl(decl) => self.function_bodies.push((decl.clone(), None)),
+            Statement::StructDecl(_) | Statement::EnumDecl(_) => {}
+            // The types of the bindings aren't known.
+            Statement::Match { value, arms, span } => {
+                self.infer(value);
+                for arm in arms {
+                    let bindings = arm.pattern.bindings().into_iter().map(|name| {
+                        let binding = Binding {
+                            constant: false,
+                            annotated: false,
+                            data_type: None,
+                        };
+                        (name, binding)
+                    });
+                    self.scopes.push(bindings.collect());
+                    self.check_statements(&arm.body);
+                    self.scopes.pop();
+                }
+                if let Some(missing) = missing_arms(arms) {
+                    let message = format!("The match doesn't cover {}.", missing);
+                    self.errors
+                        .push(CheckError::warning(*span, self.source, message));
+                }
+            }
+            Statement::Return { value, span } => {
+                let data_type = match value {
+                    Some(value) => self.infer(value),
+                    None => Some(VariableTypes::NULL),
+                };
+                match self.return_type {
+                    None => self.error(*span, "Can't return outside of a function.".to_string()),
+                    Some(Some(expected)) => {
+                        if let Some(actual) = data_type.filter(|actual| *actual != expected) {
+                            self.type_error(
+                                *span,
+                                format!(
+                                    "The function should return a value of type {:?}, got {:?}.",
+                                    expected, actual
+                                ),
+                            );
+                        }
+                    }
+                    Some(None) => {}
+                }
+            }
+            Statement::Throw { value, .. } => {
+                self.infer(value);
+            }
+            // The error a catch block gets is a struct.
+            Statement::Try {
+                body,
+                catch,
+                finally,
+                ..
+            } => {
+                self.scopes.push(HashMap::new());
+                self.check_statements(body);
+                self.scopes.pop();
+                if let Some(catch) = catch {
+                    let binding = Binding {
+                        constant: false,
+                        annotated: false,
+                        data_type: Some(VariableTypes::STRUCT),
+                    };
+                    self.scopes
+                        .push(HashMap::from([(catch.name.clone(), binding)]));
+                    self.check_statements(&catch.body);
+                    self.scopes.pop();
+                }
+                if let Some(finally) = finally {
+                    self.scopes.push(HashMap::new());
+                    self.check_statements(finally);
+                    self.scopes.pop();
+                }
+            }
+            Statement::Expression(expression) => {
+                self.infer(expression);
+            }
+            Statement::Import { name, span, .. } => {
+                let scope = self.scopes.last_mut().unwrap();
+                if scope.get(name).is_some_and(|binding| binding.constant) {
+                    self.error(*span, format!("Can't redeclare constant '{}'.", name));
+                } else {
+                    let binding = Binding {
+                        constant: true,
+                        annotated: false,
+                        data_type: Some(VariableTypes::MODULE),
+                    };
+                    scope.insert(name.clone(), binding);
+                }
+            }
+            Statement::Use { .. } | Statement::DebugVar { .. } => {}
+        }
+    }
+
+    // Functions only see the scopes they captured, the global one for named functions,
+    // and their parameters. Default values only see the parameters before them.
+    fn check_function_body(&mut self, decl: &FunctionDecl, scopes: Vec<CheckerScope>) {
+        let globals = std::mem::replace(&mut self.scopes, scopes);
+        self.scopes.push(HashMap::new());
+        for param in &decl.params {
+            if let Some(default) = &param.default {
+                let actual = self.infer(default);
+                if let (Some(expected), Some(actual)) = (param.annotation, actual) {
+                    if expected != actual {
+                        self.type_error(
+                            default.span(),
+                            format!(
+                                "The default value of parameter '{}' should be of type {:?}, got {:?}.",
+                                param.name, expected, actual
+                            ),
+                        );
+                    }
+                }
+            }
+            let binding = Binding {
+                constant: false,
+                annotated: param.annotation.is_some(),
+                data_type: param.annotation,
+            };
+            self.scopes
+                .last_mut()
+                .unwrap()
+                .insert(param.name.clone(), binding);
+        }
+        if let Some(rest) = &decl.rest {
+            let binding = Binding {
+                constant: false,
+                annotated: false,
+                data_type: Some(VariableTypes::LIST),
+            };
+            self.scopes
+                .last_mut()
+                .unwrap()
+                .insert(rest.clone(), binding);
+        }
+
+        self.return_type = Some(decl.return_type);
+        self.check_statements(&decl.body);
+        self.return_type = None;
+        self.scopes = vec![globals.into_iter().next().unwrap()];
+    }
+
+    // Gets the type of a literal, variable or function call, if it can be known.
+    fn infer(&mut self, expression: &Expression) -> Option<VariableTypes> {
+        match expression {
+            Expression::Literal { value, span } => {
+                if value.data_type == VariableTypes::INT && value.value.parse::<i64>().is_err() {
+                    let message = format!("The INT {} doesn't fit in 64 bits.", value.value);
+                    self.error(*span, message);
+                }
+                Some(value.data_type)
+            }
+            Expression::Variable { name, .. } => self
+                .find_binding(name)
+                .and_then(|(_, binding)| binding.data_type),
+            Expression::Call {
+                namespace: Some(namespace),
+                name,
+                args,
+                keywords,
+                slot,
+                span,
+            } => match slot.get() {
+                Some(_) => {
+                    let module = VariableTypes::MODULE;
+                    self.infer_variable_call(namespace, args, keywords, *span, module)
+                }
+                None => {
+                    let name = format!("{}.{}", namespace, name);
+                    self.infer_call(&name, args, keywords, *span)
+                }
+            },
+            Expression::Call {
+                name,
+                args,
+                keywords,
+                slot,
+                span,
+                ..
+            } => match slot.get() {
+                Some(_) => {
+                    let function = VariableTypes::FUNCTION;
+                    self.infer_variable_call(name, args, keywords, *span, function)
+                }
+                None => self.infer_call(name, args, keywords, *span),
+            },
+            Expression::List { items, .. } => {
+                for item in items {
+                    self.infer(item);
+                }
+                Some(VariableTypes::LIST)
+            }
+            Expression::Struct { fields, decl, .. } => {
+                let decl = decl.borrow().clone();
+                for field in fields {
+                    let actual = self.infer(&field.value);
+                    let expected = decl
+                        .iter()
+                        .flat_map(|decl| &decl.fields)
+                        .find(|f| f.name == field.name)
+                        .and_then(|f| f.annotation);
+                    if let (Some(expected), Some(actual)) = (expected, actual) {
+                        if expected != actual {
+                            self.type_error(
+                                field.value.span(),
+                                format!(
+                                    "Field '{}' of struct '{}' should be of type {:?}, got {:?}.",
+                                    field.name,
+                                    decl.as_ref().unwrap().name,
+                                    expected,
+                                    actual
+                                ),
+                            );
+                        }
+                    }
+                }
+                Some(VariableTypes::STRUCT)
+            }
+            Expression::Variant { args, .. } => {
+                for arg in args {
+                    self.infer(arg);
+                }
+                Some(VariableTypes::ENUM)
+            }
+            // The type of the field isn't known.
+            Expression::Field { value, span, .. } => {
+                if let Some(data_type) = self.infer(value) {
+                    self.check_has_fields(data_type, *span);
+                }
+                None
+            }
+            // The function can be called after the captured variables changed type.
+            Expression::Function(decl) => {
+                let mut scopes = self.scopes.clone();
+                for binding in scopes.iter_mut().flat_map(|scope| scope.values_mut()) {
+                    if !binding.constant && !binding.annotated {
+                        binding.data_type = None;
+                    }
+                }
+                self.function_bodies.push((decl.clone(), Some(scopes)));
+                Some(VariableTypes::FUNCTION)
+            }
+        }
+    }
+
+    fn check_has_fields(&mut self, data_type: VariableTypes, span: Span) {
+        if data_type != VariableTypes::STRUCT && data_type != VariableTypes::MODULE {
+            let message = format!("A value of type {:?} has no fields.", data_type);
+            self.type_error(span, message);
+        }
+    }
+
+    // What the function in the variable, or in the module of the namespace, returns isn't known.
+    fn infer_variable_call(
+        &mut self,
+        name: &str,
+        args: &[Expression],
+        keywords: &[KeywordArg],
+        span: Span,
+        expected: VariableTypes,
+    ) -> Option<VariableTypes> {
+        for arg in args {
+            self.infer(arg);
+        }
+        for keyword in keywords {
+            self.infer(&keyword.value);
+        }
+        let data_type = self
+            .find_binding(name)
+            .and_then(|(_, binding)| binding.data_type);
+        if let Some(data_type) = data_type.filter(|t| *t != expected) {
+            let message = match expected {
+                VariableTypes::MODULE => format!(
+                    "Variable '{}' is of type {:?} and isn't a module.",
+                    name, data_type
+                ),
+                _ => format!(
+                    "Variable '{}' is of type {:?} and can't be called.",
+                    name, data_type
+                ),
+            };
+            self.type_error(span, message);
+        }
+        None
+    }
+
+    fn infer_call(
+        &mut self,
+        name: &str,
+        args: &[Expression],
+        keywords: &[KeywordArg],
+        span: Span,
+    ) -> Option<VariableTypes> {
+        let arg_types: Vec<(Span, Option<VariableTypes>)> = args
+            .iter()
+            .map(|arg| (arg.span(), self.infer(arg)))
+            .collect();
+        let keyword_types: Vec<(String, (Span, Option<VariableTypes>))> = keywords
+            .iter()
+            .map(|keyword| {
+                let data_type = self.infer(&keyword.value);
+                (keyword.name.clone(), (keyword.span, data_type))
+            })
+            .collect();
+        let mut messages = Vec::new();
+
+        let return_type = if let Some(function) = self.user_functions.get(name) {
+            match match_arguments(function, arg_types, keyword_types) {
+                Ok((params, _)) => {
+                    for (param, arg) in function.params.iter().zip(params) {
+                        let (arg_span, actual) = match arg {
+                            Some((arg_span, Some(actual))) => (arg_span, actual),
+                            _ => continue,
+                        };
+                        if let Some(expected) = param.annotation.filter(|t| *t != actual) {
+                            messages.push((
+                                arg_span,
+                                format!(
+                                    "Function '{}' expects an argument of type {:?}, got {:?}.",
+                                    name, expected, actual
+                                ),
+                            ));
+                        }
+                    }
+                }
+                Err(error) => messages.push((span, error.message(&format!("'{}'", name)))),
+            }
+            function.return_type
+        } else if let Some(signature) = self
+            .builtins
+            .builtin_name(name, &self.uses)
+            .and_then(|name| self.builtins.get_signature(&name))
+        {
+            if !keywords.is_empty() {
+                messages.push((
+                    span,
+                    format!("Function '{}' doesn't take keyword arguments.", name),
+                ));
+            }
+            if args.len() < signature.min_args {
+                messages.push((
+                    span,
+                    format!(
+                        "Function '{}' takes at least {} arguments but got {}.",
+                        name,
+                        signature.min_args,
+                        args.len()
+                    ),
+                ));
+            }
+            if let Some(allowed) = &signature.arg_types {
+                for (arg_span, actual) in &arg_types {
+                    if let Some(actual) = actual.filter(|actual| !allowed.contains(actual)) {
+                        messages.push((
+                            *arg_span,
+                            format!(
+                                "Function '{}' can't take an argument of type {:?}, expected one of {:?}.",
+                                name, actual, allowed
+                            ),
+                        ));
+                    }
+                }
+            }
+            signature.return_type
+        } else {
+            messages.push((span, format!("Unknown function '{}'.", name)));
+            None
+        };
+
+        for (span, message) in messages {
+            self.type_error(span, message);
+        }
+        return_type
+    }
+}
+
+// Describes the values no arm matches, None when every value is matched. Without an arm that
+// matches anything only the variants of an enum, true and false and the lengths of lists can
+// all be covered.
+fn missing_arms(arms: &[MatchArm]) -> Option<String> {
+    if arms.iter().any(|arm| arm.pattern.irrefutable()) {
+        return None;
+    }
+    let decl = arms.iter().find_map(|arm| match &arm.pattern {
+        Pattern::Variant { decl, .. } => decl.borrow().clone(),
+        _ => None,
+    });
+    let missing: Vec<String> = if let Some(decl) = decl {
+        missing_variants(arms, &decl)
+    } else if arms
+        .iter()
+        .any(|arm| matches!(arm.pattern, Pattern::List { .. }))
+    {
+        missing_lengths(arms)?
+    } else if arms.iter().any(|arm| bool_literal(&arm.pattern).is_some()) {
+        ["true", "false"]
+            .iter()
+            .filter(|value| {
+                !arms
+                    .iter()
+                    .any(|arm| bool_literal(&arm.pattern) == Some(value))
+            })
+            .map(|value| value.to_string())
+            .collect()
+    } else {
+        return Some("every value, add a `_` arm".to_string());
+    };
+    match missing.is_empty() {
+        true => None,
+        false => Some(missing.join(", ")),
+    }
+}
+
+// A variant is covered by an arm that matches any values it holds.
+fn missing_variants(arms: &[MatchArm], decl: &EnumDecl) -> Vec<String> {
+    decl.variants
+        .iter()
+        .filter(|variant| {
+            !arms.iter().any(|arm| match &arm.pattern {
+                Pattern::Variant {
+                    enum_name,
+                    variant: name,
+                    items,
+                    ..
+                } => {
+                    *enum_name == decl.name
+                        && *name == variant.name
+                        && items.iter().all(Pattern::irrefutable)
+                }
+                _ => false,
+            })
+        })
+        .map(|variant| format!("{}::{}", decl.name, variant.name))
+        .collect()
+}
+
+// Lists are covered by an arm with a rest for the long ones and arms for every length shorter
+// than it, when their patterns match any items.
+fn missing_lengths(arms: &[MatchArm]) -> Option<Vec<String>> {
+    let lengths = |with_rest: bool| {
+        arms.iter().filter_map(move |arm| match &arm.pattern {
+            Pattern::List { items, rest }
+                if rest.is_some() == with_rest && items.iter().all(Pattern::irrefutable) =>
+            {
+                Some(items.len())
+            }
+            _ => None,
+        })
+    };
+    let shortest = match lengths(true).min() {
+        Some(shortest) => shortest,
+        None => return Some(vec!["every list, add a `[...rest]` arm".to_string()]),
+    };
+    let missing = (0..shortest)
+        .filter(|length| !lengths(false).any(|covered| covered == *length))
+        .map(|length| format!("[{}]", vec!["_"; length].join(", ")))
+        .collect();
+    Some(missing)
+}
+
+fn bool_literal(pattern: &Pattern) -> Option<&str> {
+    match pattern {
+        Pattern::Literal(value) if value.data_type == VariableTypes::BOOL => Some(&value.value),
+        _ => None,
+    }
+}
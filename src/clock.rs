@@ -0,0 +1,85 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// Where the time builtins get the time from, a host gives a fake clock to get the same
+// output on every run.
+pub trait Clock {
+    // Milliseconds since the Unix epoch, in UTC.
+    fn now(&self) -> i64;
+    // Milliseconds since the clock started, it never goes back.
+    fn monotonic(&self) -> i64;
+    fn sleep(&self, ms: u64);
+}
+
+pub struct SystemClock {
+    started: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> SystemClock {
+        SystemClock {
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> i64 {
+        match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(time) => time.as_millis() as i64,
+            Err(error) => -(error.duration().as_millis() as i64),
+        }
+    }
+
+    fn monotonic(&self) -> i64 {
+        self.started.elapsed().as_millis() as i64
+    }
+
+    fn sleep(&self, ms: u64) {
+        thread::sleep(Duration::from_millis(ms));
+    }
+}
+
+// Only moves when something sleeps or it is advanced. Clones share the time, so the embedding
+// code keeps one to move the clock the script got.
+#[derive(Clone)]
+pub struct FakeClock {
+    now: Rc<Cell<i64>>,
+    monotonic: Rc<Cell<i64>>,
+}
+
+impl FakeClock {
+    pub fn new(now: i64) -> FakeClock {
+        FakeClock {
+            now: Rc::new(Cell::new(now)),
+            monotonic: Rc::new(Cell::new(0)),
+        }
+    }
+
+    pub fn advance(&self, ms: u64) {
+        self.now.set(self.now.get() + ms as i64);
+        self.monotonic.set(self.monotonic.get() + ms as i64);
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> i64 {
+        self.now.get()
+    }
+
+    fn monotonic(&self) -> i64 {
+        self.monotonic.get()
+    }
+
+    fn sleep(&self, ms: u64) {
+        self.advance(ms);
+    }
+}
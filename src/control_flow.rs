@@ -1,68 +1,132 @@
 use crate::{
+    ast::{CatchClause, Expression, MatchArm, Statement},
+    enums::{match_pattern, no_match},
+    errors::{catch_error, error_value, raise, ErrorKind},
     execute_code::CodeExecutor,
-    functions::match_rule_func_call_decl,
-    variables::{VariableContent, VariableTypes},
-    Rule,
+    variables::{Variable, VariableContent, VariableTypes},
 };
-use pest::iterators::Pair;
 
-// Matches if statements.
-pub fn match_rule_if(if_statement: Pair<Rule>, executor: &mut CodeExecutor) {
-    // Gets the condition and makes an iterable.
-    let mut if_iter = if_statement.into_inner().into_iter();
-    let condition = if_iter.next().unwrap();
-
-    // Temp value.
-    let mut condition_value: VariableContent = VariableContent {
-        value: "".to_string(),
-        data_type: VariableTypes::NULL,
-    };
-
-    // Check for types of conditions.
-    match condition.as_rule() {
-        Rule::type_bool => {
-            condition_value.value = condition.as_str().clone().to_string();
-            condition_value.data_type = VariableTypes::BOOL;
-        }
-        Rule::var_name => {
-            let r = executor.var_container.get_variable(condition.as_str());
-            condition_value.data_type = r.data_type;
-            condition_value.value = r.value.clone();
-        }
-        Rule::func_call_decl => {
-            let r = match_rule_func_call_decl(
-                condition,
-                &executor.function_container,
-                &executor.var_container,
-            );
-            condition_value.data_type = r.data_type;
-            condition_value.value = r.value.clone();
-        }
-        _ => {
-            panic!(
-                "Not implemented condition rule for if statement: '{:?}'.",
-                condition.as_rule()
-            );
-        }
-    }
+// Executes if statements, returns the value of a return statement inside of the body.
+pub fn execute_if(
+    condition: &Expression,
+    body: &[Statement],
+    executor: &mut CodeExecutor,
+) -> Option<VariableContent> {
+    let condition_value = executor.evaluate(condition);
 
     // Execute the code.
     match condition_value.data_type {
         VariableTypes::BOOL => {
             if condition_value.value == "true" {
                 executor.var_container.scope_in();
-                for pair in if_iter {
-                    executor.execute_code(pair.into_inner());
-                }
+                let returned = executor.execute_code(body);
                 executor.var_container.scope_out();
-                return;
+                return returned;
             }
         }
-        _ => {
-            panic!(
+        _ => raise(
+            ErrorKind::Type,
+            format!(
                 "Data type '{:?}' not implemented for if condition.",
                 condition_value.data_type
-            )
+            ),
+        ),
+    }
+    None
+}
+
+// Runs the body of the first arm that matches, its bindings are the first variables of its scope.
+pub fn execute_match(
+    value: &Expression,
+    arms: &[MatchArm],
+    executor: &mut CodeExecutor,
+) -> Option<VariableContent> {
+    let value = executor.evaluate(value);
+    for arm in arms {
+        let mut bindings = Vec::new();
+        if !match_pattern(&arm.pattern, &value, &mut bindings) {
+            continue;
+        }
+
+        executor.var_container.scope_in();
+        for (index, (name, content)) in arm.pattern.bindings().into_iter().zip(bindings).enumerate()
+        {
+            let variable = Variable {
+                name,
+                content,
+                constant: false,
+                annotation: None,
+            };
+            executor.var_container.add_variable(index, variable);
         }
+        let returned = executor.execute_code(&arm.body);
+        executor.var_container.scope_out();
+        return returned;
     }
+    no_match(&value)
+}
+
+// Runs the body, an error raised in it runs the catch block with the error as its first
+// variable. The finally block runs last, also when the body returned or the error wasn't
+// caught, a return in it replaces both.
+pub fn execute_try(
+    body: &[Statement],
+    catch: Option<&CatchClause>,
+    finally: Option<&[Statement]>,
+    executor: &mut CodeExecutor,
+) -> Option<VariableContent> {
+    let mut outcome = execute_guarded(body, None, executor);
+    if let (Err(error), Some(catch)) = (&outcome, catch) {
+        let variable = Variable {
+            name: catch.name.clone(),
+            content: error.clone(),
+            constant: false,
+            annotation: None,
+        };
+        outcome = execute_guarded(&catch.body, Some(variable), executor);
+    }
+
+    if let Some(finally) = finally {
+        executor.var_container.scope_in();
+        let returned = executor.execute_code(finally);
+        executor.var_container.scope_out();
+        if returned.is_some() {
+            return returned;
+        }
+    }
+    match outcome {
+        Ok(returned) => returned,
+        Err(error) => executor.throw(error),
+    }
+}
+
+// Runs a block in its own scope, an error gives the value a catch block gets for it.
+fn execute_guarded(
+    body: &[Statement],
+    variable: Option<Variable>,
+    executor: &mut CodeExecutor,
+) -> Result<Option<VariableContent>, VariableContent> {
+    let scopes = executor.var_container.capture();
+    let module = executor.module;
+    let (depth, held) = (executor.budget.depth, executor.budget.held);
+    let result = catch_error(|| {
+        executor.var_container.scope_in();
+        if let Some(variable) = variable {
+            executor.var_container.add_variable(0, variable);
+        }
+        let returned = executor.execute_code(body);
+        executor.var_container.scope_out();
+        returned
+    });
+
+    // The error may have left the scopes and the calls of the functions it came from.
+    result.map_err(|error| {
+        executor.var_container.function_out(scopes);
+        let error = executor.locate(error);
+        executor.module = module;
+        executor.budget.depth = depth;
+        executor.budget.held = held;
+        let value = executor.thrown.take().unwrap_or_else(VariableContent::null);
+        error_value(&error, value, &executor.error_decl)
+    })
 }
@@ -0,0 +1,91 @@
+use std::rc::Rc;
+
+use crate::ast::{EnumDecl, Pattern};
+use crate::errors::{raise, ErrorKind};
+use crate::functions::values_equal;
+use crate::variables::VariableContent;
+
+// Builds a variant of the enum, the resolver already checked it takes that many values.
+pub fn build_variant(
+    decl: &Rc<EnumDecl>,
+    variant: &str,
+    payload: Vec<VariableContent>,
+) -> VariableContent {
+    let index = decl
+        .variants
+        .iter()
+        .position(|v| v.name == variant)
+        .unwrap_or_else(|| {
+            let message = format!("Enum {} has no variant {}.", decl.name, variant);
+            raise(ErrorKind::Field, message)
+        });
+    VariableContent::variant(decl.clone(), index, payload)
+}
+
+// Whether the value matches the pattern, the values of its bindings are added in the order
+// of Pattern::bindings.
+pub fn match_pattern(
+    pattern: &Pattern,
+    value: &VariableContent,
+    bindings: &mut Vec<VariableContent>,
+) -> bool {
+    match pattern {
+        Pattern::Wildcard => true,
+        Pattern::Literal(literal) => values_equal(literal, value),
+        Pattern::Binding(_) => {
+            bindings.push(value.clone());
+            true
+        }
+        Pattern::Variant {
+            enum_name,
+            variant,
+            items,
+            ..
+        } => match value.as_variant() {
+            Some((decl, index, payload))
+                if decl.name == *enum_name && decl.variants[index].name == *variant =>
+            {
+                match_all(items, payload, bindings)
+            }
+            _ => false,
+        },
+        Pattern::List { items, rest } => {
+            let values = match value.as_list() {
+                Some(values) => values,
+                None => return false,
+            };
+            let fits = match rest {
+                Some(_) => values.len() >= items.len(),
+                None => values.len() == items.len(),
+            };
+            if !fits || !match_all(items, &values[..items.len()], bindings) {
+                return false;
+            }
+            if rest.is_some() {
+                bindings.push(VariableContent::list(values[items.len()..].to_vec()));
+            }
+            true
+        }
+    }
+}
+
+fn match_all(
+    patterns: &[Pattern],
+    values: &[VariableContent],
+    bindings: &mut Vec<VariableContent>,
+) -> bool {
+    patterns.len() == values.len()
+        && patterns
+            .iter()
+            .zip(values)
+            .all(|(pattern, value)| match_pattern(pattern, value, bindings))
+}
+
+// A match without an arm for the value is an error.
+pub fn no_match(value: &VariableContent) -> ! {
+    let message = format!(
+        "No arm of the match matches {} of type {:?}.",
+        value.value, value.data_type
+    );
+    raise(ErrorKind::Match, message)
+}
@@ -0,0 +1,226 @@
+use std::any::Any;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::sync::Once;
+
+use crate::ast::{Span, StructDecl, StructField};
+use crate::variables::{VariableContent, VariableTypes};
+
+// What went wrong, a catch block gets it as the kind of the error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Thrown, // A value given to a throw statement.
+    UnknownFunction,
+    UnknownVariable,
+    Constant,
+    Type,
+    Argument,
+    Field,
+    Match,
+    Value,
+    Permission, // A builtin needs a capability the script isn't allowed.
+    Io,
+    // The limits of the script ran out.
+    InstructionLimit,
+    CallDepth,
+    ScopeDepth,
+    MemoryLimit,
+    TimeLimit,
+}
+
+const KINDS: [ErrorKind; 16] = [
+    ErrorKind::Thrown,
+    ErrorKind::UnknownFunction,
+    ErrorKind::UnknownVariable,
+    ErrorKind::Constant,
+    ErrorKind::Type,
+    ErrorKind::Argument,
+    ErrorKind::Field,
+    ErrorKind::Match,
+    ErrorKind::Value,
+    ErrorKind::Permission,
+    ErrorKind::Io,
+    ErrorKind::InstructionLimit,
+    ErrorKind::CallDepth,
+    ErrorKind::ScopeDepth,
+    ErrorKind::MemoryLimit,
+    ErrorKind::TimeLimit,
+];
+
+impl ErrorKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorKind::Thrown => "thrown",
+            ErrorKind::UnknownFunction => "unknown_function",
+            ErrorKind::UnknownVariable => "unknown_variable",
+            ErrorKind::Constant => "constant",
+            ErrorKind::Type => "type",
+            ErrorKind::Argument => "argument",
+            ErrorKind::Field => "field",
+            ErrorKind::Match => "match",
+            ErrorKind::Value => "value",
+            ErrorKind::Permission => "permission",
+            ErrorKind::Io => "io",
+            ErrorKind::InstructionLimit => "instruction_limit",
+            ErrorKind::CallDepth => "call_depth",
+            ErrorKind::ScopeDepth => "scope_depth",
+            ErrorKind::MemoryLimit => "memory_limit",
+            ErrorKind::TimeLimit => "time_limit",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<ErrorKind> {
+        KINDS.iter().copied().find(|kind| kind.name() == name)
+    }
+}
+
+// An error of the script while it runs. It unwinds to the closest try statement as the
+// payload of a panic, the executor adds where it happened when it is caught.
+#[derive(Debug, Clone)]
+pub struct ScriptError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub location: Option<Location>,
+}
+
+// The statement an error happened in, with the file it is in.
+#[derive(Debug, Clone, Default)]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl ScriptError {
+    // Errors that are thrown again keep the location they were first caught at.
+    pub fn at(mut self, file: &str, span: Span) -> ScriptError {
+        self.location.get_or_insert_with(|| Location {
+            file: file.to_string(),
+            line: span.line,
+            column: span.column,
+        });
+        self
+    }
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let location = self.location.clone().unwrap_or_default();
+        write!(
+            f,
+            "{}:{}:{}: Uncaught {} error: {}",
+            location.file,
+            location.line,
+            location.column,
+            self.kind.name(),
+            self.message
+        )
+    }
+}
+
+static QUIET_HOOK: Once = Once::new();
+
+// The default hook would print every error a try statement catches, the ones that aren't
+// caught are printed by main.
+fn install_hook() {
+    QUIET_HOOK.call_once(|| {
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if !info.payload().is::<ScriptError>() {
+                default_hook(info);
+            }
+        }));
+    });
+}
+
+pub fn raise(kind: ErrorKind, message: String) -> ! {
+    raise_error(ScriptError {
+        kind,
+        message,
+        location: None,
+    })
+}
+
+pub fn raise_error(error: ScriptError) -> ! {
+    install_hook();
+    panic::panic_any(error)
+}
+
+// Runs the function and gives back the error it raised, other panics are bugs of the
+// interpreter and keep unwinding.
+pub fn catch_error<T>(function: impl FnOnce() -> T) -> Result<T, ScriptError> {
+    install_hook();
+    panic::catch_unwind(AssertUnwindSafe(function)).map_err(|payload: Box<dyn Any + Send>| {
+        match payload.downcast::<ScriptError>() {
+            Ok(error) => *error,
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    })
+}
+
+// The struct catch blocks get, every executor makes one declaration of it.
+pub fn error_decl() -> Rc<StructDecl> {
+    let fields = ["kind", "message", "value", "file", "line", "column"]
+        .iter()
+        .map(|name| StructField {
+            name: name.to_string(),
+            annotation: None,
+        })
+        .collect();
+    Rc::new(StructDecl {
+        name: "Error".to_string(),
+        fields,
+        span: Span::default(),
+    })
+}
+
+// The value is what was thrown, NULL for the errors of the interpreter.
+pub fn error_value(
+    error: &ScriptError,
+    value: VariableContent,
+    decl: &Rc<StructDecl>,
+) -> VariableContent {
+    let location = error.location.clone().unwrap_or_default();
+    let string = |text: String| VariableContent::new(text, VariableTypes::STRING);
+    let int = |number: usize| VariableContent::new(number.to_string(), VariableTypes::INT);
+    let fields = vec![
+        string(error.kind.name().to_string()),
+        string(error.message.clone()),
+        value,
+        string(location.file),
+        int(location.line),
+        int(location.column),
+    ];
+    VariableContent::record(decl.clone(), fields)
+}
+
+// Turns a thrown value into an error and the value the catch block gets. Throwing a caught
+// error again raises it like it was.
+pub fn thrown_error(
+    value: VariableContent,
+    decl: &Rc<StructDecl>,
+) -> (ScriptError, VariableContent) {
+    if let Some((record_decl, fields)) = value.as_struct() {
+        if Rc::ptr_eq(record_decl, decl) {
+            let kind = ErrorKind::from_name(&fields[0].value).unwrap_or(ErrorKind::Thrown);
+            let location = Location {
+                file: fields[3].value.clone(),
+                line: fields[4].value.parse().unwrap_or(0),
+                column: fields[5].value.parse().unwrap_or(0),
+            };
+            let error = ScriptError {
+                kind,
+                message: fields[1].value.clone(),
+                location: Some(location),
+            };
+            return (error, fields[2].clone());
+        }
+    }
+    let error = ScriptError {
+        kind: ErrorKind::Thrown,
+        message: value.value.clone(),
+        location: None,
+    };
+    (error, value)
+}
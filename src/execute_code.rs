@@ -1,68 +1,308 @@
-use core::panic;
+use std::collections::HashMap;
+use std::rc::Rc;
 
-use pest::iterators::Pairs;
-
-use crate::control_flow::match_rule_if;
-use crate::functions::{match_rule_func_call_decl, FunctionContainer};
+use crate::ast::{Expression, Span, Statement, StructDecl};
+use crate::clock::Clock;
+use crate::control_flow::{execute_if, execute_match, execute_try};
+use crate::enums::build_variant;
+use crate::errors::{catch_error, error_decl, raise_error, thrown_error, ScriptError};
+use crate::functions::{check_debug_var, evaluate_call, FunctionContainer};
+use crate::host::Host;
+use crate::limits::{Budget, Limits};
+use crate::modules::{exports, Export, Module};
+use crate::structs::{build_struct, get_field, set_field};
 use crate::variables::{
-    match_rule_empty_var, match_rule_reassign_variable, match_rule_vardecl, VariableContainer,
+    execute_assign, execute_var_decl, FunctionValue, Variable, VariableContainer, VariableContent,
 };
-use crate::Rule;
 
 pub struct CodeExecutor {
     pub var_container: VariableContainer,
     pub function_container: FunctionContainer,
+    pub location: Span, // Of the statement running, errors are reported there.
+    pub thrown: Option<VariableContent>, // The value of the error being raised by a throw.
+    pub error_decl: Rc<StructDecl>,
+    pub budget: Budget,
+    pub host: Host,
+    pub modules: Vec<Rc<Module>>, // The file run and the files it imports.
+    pub module: usize,            // The index of the file whose code is running.
+    imported: HashMap<usize, VariableContent>, // The modules of the files that ran already.
+}
+
+impl Default for CodeExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-// Function to execute a pair of rules.
+// Function to execute the statements of the program.
 impl CodeExecutor {
     pub fn new() -> CodeExecutor {
         CodeExecutor {
             function_container: FunctionContainer::new(),
             var_container: VariableContainer::new(),
+            location: Span::default(),
+            thrown: None,
+            error_decl: error_decl(),
+            budget: Budget::new(Limits::new()),
+            host: Host::new(),
+            modules: Vec::new(),
+            module: 0,
+            imported: HashMap::new(),
         }
     }
 
-    pub fn execute_code(&mut self, lines: Pairs<Rule>) {
-        // Loop through all the pairs.
-        for line in lines {
-            match line.as_rule() {
-                // Passes the pair to a function to keep this code clean.
-                Rule::var_decl_assign => {
-                    match_rule_vardecl(line, &mut self.var_container, &self.function_container);
-                }
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.var_container.max_depth = limits.scope_depth;
+        self.budget = Budget::new(limits);
+    }
+
+    // The time builtins read this clock, a FakeClock gives the same times on every run.
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.host.clock = clock;
+    }
+
+    // Runs the first file, giving back the error that no try statement caught.
+    pub fn run(&mut self, modules: Vec<Rc<Module>>) -> Result<(), ScriptError> {
+        self.modules = modules;
+        self.budget.start(&*self.host.clock);
+        self.host.deadline = self.budget.deadline();
+        let main = self.modules[0].clone();
+        catch_error(|| self.execute_code(&main.program))
+            .map(|_| ())
+            .map_err(|error| self.locate(error))
+    }
+
+    // Adds the statement that was running to the error.
+    pub fn locate(&self, error: ScriptError) -> ScriptError {
+        error.at(&self.modules[self.module].path, self.location)
+    }
+
+    // The bytes of the variables a call leaves behind while it runs.
+    pub fn held_memory(&self) -> usize {
+        match self.budget.counts_memory() {
+            true => self.var_container.size(),
+            false => 0,
+        }
+    }
+
+    // Runs the file the first time it is imported with its own globals, every import gets the
+    // same module.
+    fn import(&mut self, module: usize) -> VariableContent {
+        if let Some(imported) = self.imported.get(&module) {
+            return imported.clone();
+        }
+        let file = self.modules[module].clone();
+        let held = self.held_memory();
+        self.budget.call_in(held);
+        let caller_scopes = self.var_container.function_in(Vec::new());
+        let caller_module = std::mem::replace(&mut self.module, module);
+        let caller_location = self.location;
+        self.execute_code(&file.program);
 
-                Rule::var_empty_decl => match_rule_empty_var(line, &mut self.var_container),
-                Rule::var_reassign_decl => {
-                    match_rule_reassign_variable(
-                        line,
-                        &mut self.var_container,
-                        &self.function_container,
-                    );
+        let globals = self.var_container.globals();
+        let exports = exports(&file.program)
+            .into_iter()
+            .map(|(name, export)| {
+                let value = match export {
+                    Export::Function(decl) => {
+                        let function = FunctionValue::Closure {
+                            decl: decl.clone(),
+                            scopes: globals.clone(),
+                            module,
+                        };
+                        VariableContent::function(function, &decl.param_names())
+                    }
+                    Export::Constant(index) => globals[0].borrow()[index].content.clone(),
+                };
+                (name.to_string(), value)
+            })
+            .collect();
+        self.var_container.function_out(caller_scopes);
+        self.module = caller_module;
+        self.location = caller_location;
+        self.budget.call_out(held);
+
+        let imported = VariableContent::module(&file.path, exports);
+        self.imported.insert(module, imported.clone());
+        imported
+    }
+
+    // Returns the value of a return statement, the caller has to stop executing when it gets one.
+    pub fn execute_code(&mut self, statements: &[Statement]) -> Option<VariableContent> {
+        // Loop through all the statements.
+        for statement in statements {
+            self.location = statement.span();
+            self.budget.step(&*self.host.clock);
+            if self.budget.counts_memory() {
+                self.budget.count_memory(self.var_container.size());
+            }
+            match statement {
+                // Passes the statement to a function to keep this code clean.
+                Statement::VarDecl {
+                    name,
+                    annotation,
+                    constant,
+                    value,
+                    index,
+                    ..
+                } => {
+                    let index = index.get().expect("Variable wasn't resolved.");
+                    execute_var_decl(name, *annotation, *constant, value.as_ref(), index, self)
                 }
-                Rule::func_call_decl => {
-                    match_rule_func_call_decl(
-                        line,
-                        &self.function_container,
-                        &mut self.var_container,
-                    );
+                Statement::Assign {
+                    name, value, slot, ..
+                } => {
+                    let slot = slot.get().expect("Variable wasn't resolved.");
+                    execute_assign(name, value, slot, self)
                 }
-                Rule::control_if => match_rule_if(line, self),
-                Rule::debug => {
-                    // Debug rules.
-                    let debug_what = line.into_inner().into_iter().next().unwrap().as_rule();
-                    match debug_what {
-                        Rule::debug_var => {
-                            self.var_container.debug_print_vars();
-                        }
-                        _ => {
-                            panic!("Unsupported debug rule '{:?}'.", debug_what);
-                        }
+                // The variable gets a copy of the struct with the field changed.
+                Statement::FieldAssign {
+                    name,
+                    path,
+                    value,
+                    slot,
+                    ..
+                } => {
+                    let slot = slot.get().expect("Variable wasn't resolved.");
+                    let value = self.evaluate(value);
+                    let record =
+                        set_field(&self.var_container.get_variable(slot, name), path, value);
+                    self.budget.check_size(&record);
+                    self.var_container.set_variable(slot, name, record);
+                }
+                Statement::Expression(expression) => {
+                    self.evaluate(expression);
+                }
+                Statement::FunctionDecl(decl) => self
+                    .function_container
+                    .add_user_function(self.module, decl.clone()),
+                // The resolver already gave every struct literal and variant its declaration.
+                Statement::StructDecl(_) | Statement::EnumDecl(_) => {}
+                Statement::Return { value, .. } => {
+                    let value = match value {
+                        Some(value) => self.evaluate(value),
+                        None => VariableContent::null(),
+                    };
+                    return Some(value);
+                }
+                Statement::If {
+                    condition, body, ..
+                } => {
+                    if let Some(returned) = execute_if(condition, body, self) {
+                        return Some(returned);
                     }
                 }
-                _ => {
-                    panic!("Rule '{:?}' not implemented.", line.as_rule());
+                Statement::Match { value, arms, .. } => {
+                    if let Some(returned) = execute_match(value, arms, self) {
+                        return Some(returned);
+                    }
+                }
+                Statement::Throw { value, .. } => {
+                    let value = self.evaluate(value);
+                    self.throw(value);
                 }
+                Statement::Try {
+                    body,
+                    catch,
+                    finally,
+                    ..
+                } => {
+                    let finally = finally.as_deref();
+                    if let Some(returned) = execute_try(body, catch.as_ref(), finally, self) {
+                        return Some(returned);
+                    }
+                }
+                Statement::Import {
+                    name,
+                    module,
+                    index,
+                    ..
+                } => {
+                    let module = module.get().expect("Import wasn't loaded.");
+                    let index = index.get().expect("Variable wasn't resolved.");
+                    let variable = Variable {
+                        name: name.clone(),
+                        content: self.import(module),
+                        constant: true,
+                        annotation: None,
+                    };
+                    self.var_container.add_variable(index, variable);
+                }
+                // The builtins of the used modules are looked up for the whole file.
+                Statement::Use { .. } => {}
+                Statement::DebugVar { .. } => {
+                    check_debug_var(self.function_container.capabilities());
+                    self.var_container.debug_print_vars(&mut self.host);
+                }
+            }
+        }
+        None
+    }
+
+    // Raises the value as an error, the value is kept aside for the catch block.
+    pub fn throw(&mut self, value: VariableContent) -> ! {
+        let (error, value) = thrown_error(value, &self.error_decl);
+        self.thrown = Some(value);
+        raise_error(error)
+    }
+
+    // Gets the value of a literal, variable or function call.
+    pub fn evaluate(&mut self, expression: &Expression) -> VariableContent {
+        let value = self.evaluate_expression(expression);
+        self.budget.check_size(&value);
+        value
+    }
+
+    fn evaluate_expression(&mut self, expression: &Expression) -> VariableContent {
+        match expression {
+            Expression::Literal { value, .. } => value.clone(),
+            // The value is copied out of the scope, which can change before it is used.
+            Expression::Variable { name, slot, .. } => {
+                let slot = slot.get().expect("Variable wasn't resolved.");
+                self.var_container.get_variable(slot, name).clone()
+            }
+            Expression::Call {
+                namespace,
+                name,
+                args,
+                keywords,
+                slot,
+                ..
+            } => {
+                let namespace = namespace.as_deref();
+                evaluate_call(namespace, name, slot.get(), args, keywords, self)
+            }
+            Expression::List { items, .. } => {
+                let items = items.iter().map(|item| self.evaluate(item)).collect();
+                VariableContent::list(items)
+            }
+            Expression::Struct { fields, decl, .. } => {
+                let decl = decl.borrow().clone().expect("Struct wasn't resolved.");
+                let values = fields
+                    .iter()
+                    .map(|field| (field.name.clone(), self.evaluate(&field.value)))
+                    .collect();
+                build_struct(&decl, values)
+            }
+            Expression::Field { value, field, .. } => get_field(&self.evaluate(value), field),
+            Expression::Variant {
+                variant,
+                args,
+                decl,
+                ..
+            } => {
+                let decl = decl.borrow().clone().expect("Enum wasn't resolved.");
+                let payload = args.iter().map(|arg| self.evaluate(arg)).collect();
+                build_variant(&decl, variant, payload)
+            }
+            // Function literals capture the scopes they are in, so they can use their variables.
+            Expression::Function(decl) => {
+                let function = FunctionValue::Closure {
+                    decl: decl.clone(),
+                    scopes: self.var_container.capture(),
+                    module: self.module,
+                };
+                VariableContent::function(function, &decl.param_names())
             }
         }
     }
@@ -1,152 +1,757 @@
-use core::panic;
 use std::collections::HashMap;
-use std::io::{self, Write};
+use std::fmt;
+use std::rc::Rc;
 
-use pest::iterators::Pair;
+use crate::ast::{Expression, FunctionDecl, KeywordArg, Param, Slot};
+use crate::buildin_functions::file_functions::{
+    file_append, file_exists, file_list_dir, file_mkdir, file_read, file_remove, file_write,
+};
+use crate::buildin_functions::format_functions::{simple_format, simple_printf};
+use crate::buildin_functions::json_functions::{json_parse, json_stringify};
+use crate::buildin_functions::list_functions::{
+    simple_all, simple_any, simple_enumerate, simple_filter, simple_map, simple_reduce,
+    simple_sort, simple_zip,
+};
+use crate::buildin_functions::math_functions::{
+    math_abs, math_ceil, math_floor, math_max, math_min, math_pow, math_round, math_sqrt,
+    simple_add, simple_sub,
+};
+use crate::buildin_functions::random_functions::{
+    random_choice, random_rand_float, random_rand_int, random_seed, random_shuffle,
+};
+use crate::buildin_functions::string_functions::{
+    simple_concat, str_contains, str_len, str_lower, str_replace, str_split, str_trim, str_upper,
+};
+use crate::buildin_functions::system_functions::{
+    simple_env, simple_input, simple_read_all, simple_read_line,
+};
+use crate::buildin_functions::time_functions::{
+    time_between, time_days, time_format_duration, time_from_iso, time_hours, time_minutes,
+    time_monotonic, time_now, time_seconds, time_shift, time_sleep, time_to_iso,
+};
+use crate::capabilities::{Capabilities, Capability};
+use crate::errors::{raise, ErrorKind};
+use crate::execute_code::CodeExecutor;
+use crate::host::Host;
+use crate::structs::get_field;
+use crate::variables::{FunctionValue, Object, Scope, Variable, VariableContent, VariableTypes};
 
-use crate::buildin_functions::math_functions::{simple_add, simple_sub};
-use crate::type_string::make_string;
-use crate::variables::{VariableContainer, VariableContent, VariableTypes};
-use crate::Rule;
+pub type SimpleFunction = fn(Vec<VariableContent>) -> VariableContent;
 
-type SimpleFunction = fn(Vec<VariableContent>) -> VariableContent;
+// Calls a function value, or the function named by a string, for the builtins that take one.
+pub type FunctionCaller<'a> =
+    dyn FnMut(&VariableContent, Vec<VariableContent>) -> VariableContent + 'a;
+pub type HigherOrderFunction = fn(Vec<VariableContent>, &mut FunctionCaller) -> VariableContent;
+// Gets what the executor reaches outside of the script through.
+pub type HostFunction = fn(Vec<VariableContent>, &mut Host) -> VariableContent;
+
+// A function of a builtin module: the module, its name, min_args, arg_types and return_type.
+type ModuleFunction = (
+    &'static str,
+    &'static str,
+    SimpleFunction,
+    usize,
+    Option<Vec<VariableTypes>>,
+    Option<VariableTypes>,
+);
+
+// A host function of a builtin module: its name, min_args, arg_types and return_type.
+type HostModuleFunction = (
+    &'static str,
+    HostFunction,
+    usize,
+    Option<Vec<VariableTypes>>,
+    Option<VariableTypes>,
+);
+
+#[derive(Clone, Copy)]
+pub enum Builtin {
+    Simple(SimpleFunction),
+    HigherOrder(HigherOrderFunction), // Gets a way to call the functions it is given.
+    Host(HostFunction),
+    Denied(Capability), // Calling it is a permission error.
+}
+
+impl fmt::Debug for Builtin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Builtin::Simple(_) => write!(f, "Simple"),
+            Builtin::HigherOrder(_) => write!(f, "HigherOrder"),
+            Builtin::Host(_) => write!(f, "Host"),
+            Builtin::Denied(capability) => write!(f, "Denied({})", capability.name()),
+        }
+    }
+}
+
+// Raises the error for calling a builtin the script isn't allowed to use.
+pub fn denied(func_name: &str, capability: Capability) -> ! {
+    let message = format!(
+        "Function {} needs the capability '{}'.",
+        func_name,
+        capability.name()
+    );
+    raise(ErrorKind::Permission, message)
+}
+
+// debug.var prints like print, so it needs the same capability.
+pub fn check_debug_var(capabilities: &Capabilities) {
+    if !capabilities.allows(Capability::Stdout) {
+        let message = "Statement debug.var needs the capability 'stdout'.".to_string();
+        raise(ErrorKind::Permission, message);
+    }
+}
+
+// What is known about a builtin without calling it, used by the type checker.
+pub struct FunctionSignature {
+    pub min_args: usize,
+    pub arg_types: Option<Vec<VariableTypes>>, // The types every argument may have, None allows any type.
+    pub return_type: Option<VariableTypes>,    // None when it depends on the arguments.
+    pub pure: bool, // Without side effects, so the optimizer may call it before running.
+    pub capabilities: Vec<Capability>, // What it touches outside of the script.
+}
 
 pub struct FunctionContainer {
-    functions: HashMap<String, SimpleFunction>,
+    functions: HashMap<String, (Builtin, FunctionSignature)>, // Functions of modules are like math.sqrt.
+    modules: HashMap<String, Vec<String>>, // The builtin modules with the names of their functions.
+    capabilities: Capabilities,            // The builtins needing others are denied.
+    // The functions declared in the script, by the index of their file and their name.
+    user_functions: HashMap<(usize, String), Rc<FunctionDecl>>,
 }
 
 impl FunctionContainer {
     pub fn new() -> FunctionContainer {
-        let mut loaded_func: HashMap<String, SimpleFunction> = HashMap::new();
-        loaded_func.insert("print".to_string(), simple_print);
-        loaded_func.insert("eq".to_string(), simple_eq);
-        loaded_func.insert("add".to_string(), simple_add);
-        loaded_func.insert("sub".to_string(), simple_sub);
-        FunctionContainer {
-            functions: loaded_func,
+        FunctionContainer::with_capabilities(Capabilities::all())
+    }
+
+    // A host running scripts it doesn't trust only gives them some of the capabilities.
+    pub fn with_capabilities(capabilities: Capabilities) -> FunctionContainer {
+        let mut container = FunctionContainer {
+            functions: HashMap::new(),
+            modules: HashMap::new(),
+            capabilities,
+            user_functions: HashMap::new(),
+        };
+        let numbers = Some(vec![VariableTypes::INT, VariableTypes::FLOAT]);
+        let (float, string) = (Some(VariableTypes::FLOAT), Some(VariableTypes::STRING));
+        let (bool, list) = (Some(VariableTypes::BOOL), Some(VariableTypes::LIST));
+        let null = Some(VariableTypes::NULL);
+        container.add_builtin("print", Builtin::Host(simple_print), 0, None, null, false);
+        container.require("print", Capability::Stdout);
+        container.add_builtin("eq", Builtin::Simple(simple_eq), 2, None, bool, true);
+        container.add_builtin(
+            "add",
+            Builtin::Simple(simple_add),
+            2,
+            numbers.clone(),
+            float,
+            true,
+        );
+        container.add_builtin("sub", Builtin::Simple(simple_sub), 2, numbers, float, true);
+        container.add_builtin(
+            "concat",
+            Builtin::Simple(simple_concat),
+            0,
+            None,
+            string,
+            true,
+        );
+        let format = Builtin::Simple(simple_format);
+        container.add_builtin("format", format, 1, None, string, true);
+        container.add_builtin("printf", Builtin::Host(simple_printf), 1, None, null, false);
+        container.require("printf", Capability::Stdout);
+        container.add_builtin("env", Builtin::Simple(simple_env), 1, None, None, false);
+        container.require("env", Capability::Env);
+
+        // The input of the host, stdin unless it was given another reader.
+        let input: [(&str, HostFunction, usize); 3] = [
+            ("input", simple_input, 1),
+            ("read_line", simple_read_line, 0),
+            ("read_all", simple_read_all, 0),
+        ];
+        for (name, function, min_args) in input {
+            let function = Builtin::Host(function);
+            container.add_builtin(name, function, min_args, None, None, false);
+            container.require(name, Capability::Stdin);
+        }
+        // The prompt is printed.
+        container.require("input", Capability::Stdout);
+
+        // The files in the root directory of the host.
+        let strings = Some(vec![VariableTypes::STRING]);
+        let files: [(&str, HostFunction, usize, Option<VariableTypes>, Capability); 7] = [
+            ("read_file", file_read, 1, string, Capability::FsRead),
+            ("write_file", file_write, 2, null, Capability::FsWrite),
+            ("append_file", file_append, 2, null, Capability::FsWrite),
+            ("exists", file_exists, 1, bool, Capability::FsRead),
+            ("list_dir", file_list_dir, 1, list, Capability::FsRead),
+            ("mkdir", file_mkdir, 1, null, Capability::FsWrite),
+            ("remove", file_remove, 1, null, Capability::FsWrite),
+        ];
+        for (name, function, min_args, return_type, capability) in files {
+            let function = Builtin::Host(function);
+            let arg_types = strings.clone();
+            container.add_builtin(name, function, min_args, arg_types, return_type, false);
+            container.require(name, capability);
+        }
+
+        // These take a function value or the name of a function.
+        let higher_order: [(&str, HigherOrderFunction, usize, Option<VariableTypes>); 8] = [
+            ("map", simple_map, 2, list),
+            ("filter", simple_filter, 2, list),
+            ("reduce", simple_reduce, 2, None),
+            ("any", simple_any, 1, bool),
+            ("all", simple_all, 1, bool),
+            ("sort", simple_sort, 1, list),
+            ("enumerate", simple_enumerate, 1, list),
+            ("zip", simple_zip, 1, list),
+        ];
+        for (name, function, min_args, return_type) in higher_order {
+            let function = Builtin::HigherOrder(function);
+            container.add_builtin(name, function, min_args, None, return_type, false);
+        }
+
+        // The builtin modules, called like math.sqrt(2) or after `use math` like sqrt(2).
+        let numbers = Some(vec![VariableTypes::INT, VariableTypes::FLOAT]);
+        let strings = Some(vec![VariableTypes::STRING]);
+        let int = Some(VariableTypes::INT);
+        let module_functions: [ModuleFunction; 15] = [
+            ("math", "sqrt", math_sqrt, 1, numbers.clone(), float),
+            ("math", "pow", math_pow, 2, numbers.clone(), float),
+            ("math", "abs", math_abs, 1, numbers.clone(), None),
+            ("math", "floor", math_floor, 1, numbers.clone(), int),
+            ("math", "ceil", math_ceil, 1, numbers.clone(), int),
+            ("math", "round", math_round, 1, numbers.clone(), int),
+            ("math", "min", math_min, 1, numbers.clone(), None),
+            ("math", "max", math_max, 1, numbers, None),
+            ("str", "upper", str_upper, 1, strings.clone(), string),
+            ("str", "lower", str_lower, 1, strings.clone(), string),
+            ("str", "trim", str_trim, 1, strings.clone(), string),
+            ("str", "len", str_len, 1, strings.clone(), int),
+            ("str", "contains", str_contains, 2, strings.clone(), bool),
+            ("str", "split", str_split, 2, strings.clone(), list),
+            ("str", "replace", str_replace, 3, strings, string),
+        ];
+        for (module, name, function, min_args, arg_types, return_type) in module_functions {
+            let name = container.add_to_module(module, name);
+            let function = Builtin::Simple(function);
+            container.add_builtin(&name, function, min_args, arg_types, return_type, true);
+        }
+        // Parsing makes structs, which the optimizer can't write back as literals.
+        let name = container.add_to_module("json", "parse");
+        let (parse, strings) = (
+            Builtin::Simple(json_parse),
+            Some(vec![VariableTypes::STRING]),
+        );
+        container.add_builtin(&name, parse, 1, strings, None, false);
+        let name = container.add_to_module("json", "stringify");
+        let stringify = Builtin::Simple(json_stringify);
+        container.add_builtin(&name, stringify, 1, None, string, true);
+
+        // The random numbers of the host, the same for the same seed.
+        let (ints, lists) = (
+            Some(vec![VariableTypes::INT]),
+            Some(vec![VariableTypes::LIST]),
+        );
+        let random: [HostModuleFunction; 5] = [
+            ("rand_int", random_rand_int, 2, ints.clone(), int),
+            ("rand_float", random_rand_float, 0, None, float),
+            ("choice", random_choice, 1, lists.clone(), None),
+            ("shuffle", random_shuffle, 1, lists, list),
+            ("seed", random_seed, 1, ints.clone(), null),
+        ];
+        for (name, function, min_args, arg_types, return_type) in random {
+            let name = container.add_to_module("random", name);
+            let function = Builtin::Host(function);
+            container.add_builtin(&name, function, min_args, arg_types, return_type, false);
+            container.require(&name, Capability::Random);
+        }
+
+        // The clock of the host, times and durations are INTs of milliseconds.
+        let clock: [HostModuleFunction; 3] = [
+            ("now", time_now, 0, None, int),
+            ("monotonic", time_monotonic, 0, None, int),
+            ("sleep", time_sleep, 1, ints.clone(), null),
+        ];
+        for (name, function, min_args, arg_types, return_type) in clock {
+            let name = container.add_to_module("time", name);
+            let function = Builtin::Host(function);
+            container.add_builtin(&name, function, min_args, arg_types, return_type, false);
+            container.require(&name, Capability::Time);
+        }
+        let numbers = Some(vec![VariableTypes::INT, VariableTypes::FLOAT]);
+        let strings = Some(vec![VariableTypes::STRING]);
+        let time_functions: [ModuleFunction; 9] = [
+            ("time", "seconds", time_seconds, 1, numbers.clone(), int),
+            ("time", "minutes", time_minutes, 1, numbers.clone(), int),
+            ("time", "hours", time_hours, 1, numbers.clone(), int),
+            ("time", "days", time_days, 1, numbers, int),
+            ("time", "shift", time_shift, 2, ints.clone(), int),
+            ("time", "between", time_between, 2, ints.clone(), int),
+            (
+                "time",
+                "format_duration",
+                time_format_duration,
+                1,
+                ints.clone(),
+                string,
+            ),
+            ("time", "to_iso", time_to_iso, 1, ints, string),
+            ("time", "from_iso", time_from_iso, 1, strings, int),
+        ];
+        for (module, name, function, min_args, arg_types, return_type) in time_functions {
+            let name = container.add_to_module(module, name);
+            let function = Builtin::Simple(function);
+            container.add_builtin(&name, function, min_args, arg_types, return_type, true);
+        }
+        container
+    }
+
+    // Gives the name the function of the module is called by.
+    fn add_to_module(&mut self, module: &str, name: &str) -> String {
+        self.modules
+            .entry(module.to_string())
+            .or_default()
+            .push(name.to_string());
+        format!("{}.{}", module, name)
+    }
+
+    fn add_builtin(
+        &mut self,
+        name: &str,
+        function: Builtin,
+        min_args: usize,
+        arg_types: Option<Vec<VariableTypes>>,
+        return_type: Option<VariableTypes>,
+        pure: bool,
+    ) {
+        let signature = FunctionSignature {
+            min_args,
+            arg_types,
+            return_type,
+            pure,
+            capabilities: Vec::new(),
+        };
+        self.functions
+            .insert(name.to_string(), (function, signature));
+    }
+
+    fn require(&mut self, name: &str, capability: Capability) {
+        let (_, signature) = self.functions.get_mut(name).unwrap();
+        signature.capabilities.push(capability);
+    }
+
+    pub fn capabilities(&self) -> &Capabilities {
+        &self.capabilities
+    }
+
+    // A builtin needing a capability that isn't allowed is still found, calling it fails.
+    pub fn get_builtin(&self, func_name: &str) -> Option<Builtin> {
+        let (function, signature) = self.functions.get(func_name)?;
+        let missing = signature
+            .capabilities
+            .iter()
+            .find(|capability| !self.capabilities.allows(**capability));
+        match missing {
+            Some(capability) => Some(Builtin::Denied(*capability)),
+            None => Some(*function),
+        }
+    }
+
+    pub fn has_module(&self, module: &str) -> bool {
+        self.modules.contains_key(module)
+    }
+
+    // The name of the builtin a call refers to, the modules of `use` statements are searched
+    // in order after the builtins outside of modules.
+    pub fn builtin_name(&self, func_name: &str, uses: &[String]) -> Option<String> {
+        if self.functions.contains_key(func_name) {
+            return Some(func_name.to_string());
         }
+        uses.iter()
+            .filter(|module| {
+                self.modules
+                    .get(*module)
+                    .is_some_and(|names| names.iter().any(|name| name == func_name))
+            })
+            .map(|module| format!("{}.{}", module, func_name))
+            .next()
     }
 
-    pub fn call_function(&self, func_name: &str, args: Vec<VariableContent>) -> VariableContent {
-        let function = *self
-            .functions
+    pub fn get_signature(&self, func_name: &str) -> Option<&FunctionSignature> {
+        self.functions
             .get(func_name)
-            .expect(format!("Unknown function '{}'.", func_name).as_str());
-        function(args)
+            .map(|(_, signature)| signature)
+    }
+
+    // Adds a function declared in the script, every file has its own functions.
+    pub fn add_user_function(&mut self, module: usize, decl: Rc<FunctionDecl>) {
+        if self.functions.contains_key(&decl.name) {
+            let message = format!("Can't declare function '{}' as it is a builtin.", decl.name);
+            raise(ErrorKind::Constant, message);
+        }
+        self.user_functions
+            .insert((module, decl.name.clone()), decl);
+    }
+
+    pub fn get_user_function(&self, module: usize, func_name: &str) -> Option<Rc<FunctionDecl>> {
+        self.user_functions
+            .get(&(module, func_name.to_string()))
+            .cloned()
     }
 }
 
-// Matches the functions.
-pub fn match_rule_func_call_decl(
-    pair: Pair<Rule>,
-    function_container: &FunctionContainer,
-    variable_container: &VariableContainer,
+impl Default for FunctionContainer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Evaluates the arguments and calls the function, the slot is set when it is a variable or
+// when the function is in the module of the namespace.
+pub fn evaluate_call(
+    namespace: Option<&str>,
+    func_name: &str,
+    slot: Option<Slot>,
+    arg_expressions: &[Expression],
+    keyword_expressions: &[KeywordArg],
+    executor: &mut CodeExecutor,
+) -> VariableContent {
+    // Without a slot the namespace is a builtin module.
+    let (callee, func_name) = match (namespace, slot) {
+        (Some(namespace), Some(slot)) => {
+            let callee = get_field(
+                &executor.var_container.get_variable(slot, namespace),
+                func_name,
+            );
+            (Some(callee), format!("{}.{}", namespace, func_name))
+        }
+        (Some(namespace), None) => (None, format!("{}.{}", namespace, func_name)),
+        (None, _) => {
+            let callee =
+                slot.map(|slot| executor.var_container.get_variable(slot, func_name).clone());
+            (callee, func_name.to_string())
+        }
+    };
+    let func_name = func_name.as_str();
+    let args: Vec<VariableContent> = arg_expressions
+        .iter()
+        .map(|arg| executor.evaluate(arg))
+        .collect();
+    let keywords: Vec<(String, VariableContent)> = keyword_expressions
+        .iter()
+        .map(|keyword| (keyword.name.clone(), executor.evaluate(&keyword.value)))
+        .collect();
+
+    match callee {
+        Some(callee) if callee.data_type == VariableTypes::FUNCTION => {
+            call_function_value(&callee, args, keywords, executor)
+        }
+        Some(callee) => raise(
+            ErrorKind::Type,
+            format!(
+                "Variable {} is of type {:?} and can't be called.",
+                func_name, callee.data_type
+            ),
+        ),
+        None => call_named_function(func_name, args, keywords, executor),
+    }
+}
+
+// Calls the function, user functions can't have the name of a builtin.
+fn call_named_function(
+    func_name: &str,
+    args: Vec<VariableContent>,
+    keywords: Vec<(String, VariableContent)>,
+    executor: &mut CodeExecutor,
+) -> VariableContent {
+    let module = executor.module;
+    if let Some(decl) = executor
+        .function_container
+        .get_user_function(module, func_name)
+    {
+        let globals = executor.var_container.globals();
+        return call_user_function(&decl, globals, module, args, keywords, executor);
+    }
+    let uses = &executor.modules[module].uses;
+    let builtin = executor
+        .function_container
+        .builtin_name(func_name, uses)
+        .and_then(|name| executor.function_container.get_builtin(&name));
+    if builtin.is_some() && !keywords.is_empty() {
+        let message = format!("Function {} doesn't take keyword arguments.", func_name);
+        raise(ErrorKind::Argument, message);
+    }
+    match builtin {
+        Some(Builtin::Simple(function)) => function(args),
+        Some(Builtin::HigherOrder(function)) => function(args, &mut |callee, args| {
+            call_function_value(callee, args, Vec::new(), executor)
+        }),
+        Some(Builtin::Host(function)) => {
+            let result = function(args, &mut executor.host);
+            executor.budget.check_time(&*executor.host.clock);
+            result
+        }
+        Some(Builtin::Denied(capability)) => denied(func_name, capability),
+        None => raise(
+            ErrorKind::UnknownFunction,
+            format!("Unknown function '{}'.", func_name),
+        ),
+    }
+}
+
+// Calls a function value, a string calls the function with that name.
+pub fn call_function_value(
+    callee: &VariableContent,
+    args: Vec<VariableContent>,
+    keywords: Vec<(String, VariableContent)>,
+    executor: &mut CodeExecutor,
+) -> VariableContent {
+    match callee.as_function() {
+        Some(FunctionValue::Closure {
+            decl,
+            scopes,
+            module,
+        }) => call_user_function(decl, scopes.clone(), *module, args, keywords, executor),
+        _ if callee.data_type == VariableTypes::STRING => {
+            call_named_function(&callee.value, args, keywords, executor)
+        }
+        _ => raise(
+            ErrorKind::Type,
+            format!(
+                "Expected a function or the name of one, got {:?}.",
+                callee.data_type
+            ),
+        ),
+    }
+}
+
+// Runs the body of a function declared in the script, in the scopes it can see and with the
+// functions of its file.
+fn call_user_function(
+    decl: &FunctionDecl,
+    scopes: Vec<Scope>,
+    module: usize,
+    args: Vec<VariableContent>,
+    keywords: Vec<(String, VariableContent)>,
+    executor: &mut CodeExecutor,
 ) -> VariableContent {
-    let mut args: Vec<VariableContent> = Vec::new();
-    let mut func = "".to_string();
-    let pair_inner = pair.into_inner();
-
-    // Gets function and arguments.
-    for p in pair_inner {
-        match p.as_rule() {
-            Rule::func_call_name => func = p.as_str().to_string(),
-            Rule::func_call_args => {
-                for px in p.into_inner() {
-                    match px.as_rule() {
-                        Rule::type_int => {
-                            args.push(VariableContent {
-                                value: px.as_str().to_string(),
-                                data_type: VariableTypes::INT,
-                            });
-                        }
-                        Rule::type_bool => {
-                            args.push(VariableContent {
-                                value: px.as_str().to_string(),
-                                data_type: VariableTypes::BOOL,
-                            });
-                        }
-                        Rule::type_string => args.push(VariableContent {
-                            value: make_string(px.as_str()),
-                            data_type: VariableTypes::STRING,
-                        }),
-                        Rule::type_float => args.push(VariableContent {
-                            value: px.as_str().to_string(),
-                            data_type: VariableTypes::FLOAT,
-                        }),
-                        Rule::var_name => {
-                            // Gets the variable then copies it because it is a shared reference.
-                            let var = variable_container.get_variable(px.as_str());
-                            let var_to_push = VariableContent {
-                                data_type: var.data_type,
-                                value: var.value.clone(),
-                            };
-                            args.push(var_to_push);
-                        }
-                        Rule::func_call_decl => {
-                            let r = match_rule_func_call_decl(
-                                px,
-                                function_container,
-                                variable_container,
-                            );
-                            let to_push = VariableContent {
-                                data_type: r.data_type,
-                                value: r.value.clone(),
-                            };
-                            args.push(to_push);
-                        }
-                        _ => panic!("Rule {:?} not implemented for functions.", px.as_rule()),
-                    }
-                }
+    let (params, rest) = bind_arguments(decl, args, keywords);
+    let held = executor.held_memory();
+    executor.budget.call_in(held);
+
+    // Default values are evaluated in the function, they can use the parameters before them.
+    // Errors in them are reported at the declaration.
+    let caller_scopes = executor.var_container.function_in(scopes);
+    let caller_location = std::mem::replace(&mut executor.location, decl.span);
+    let caller_module = std::mem::replace(&mut executor.module, module);
+    for (index, (param, arg)) in decl.params.iter().zip(params).enumerate() {
+        let content = match (arg, &param.default) {
+            (Some(arg), _) => arg,
+            (None, Some(default)) => {
+                let value = executor.evaluate(default);
+                check_argument(decl, param, value.data_type);
+                value
             }
-            _ => {}
+            (None, None) => unreachable!("Missing arguments are found when binding them."),
+        };
+        let variable = Variable {
+            name: param.name.clone(),
+            content,
+            constant: false,
+            annotation: param.annotation,
+        };
+        executor.var_container.add_variable(index, variable);
+    }
+    if let (Some(name), Some(rest)) = (&decl.rest, rest) {
+        let variable = Variable {
+            name: name.clone(),
+            content: rest,
+            constant: false,
+            annotation: None,
+        };
+        executor
+            .var_container
+            .add_variable(decl.params.len(), variable);
+    }
+
+    let result = executor
+        .execute_code(&decl.body)
+        .unwrap_or_else(VariableContent::null);
+    executor.var_container.function_out(caller_scopes);
+    executor.location = caller_location;
+    executor.module = caller_module;
+    executor.budget.call_out(held);
+
+    check_return(decl, result.data_type);
+    result
+}
+
+// Why the arguments of a call don't fit the parameters of a function.
+#[derive(Debug)]
+pub enum ArgumentError {
+    TooMany {
+        max: usize,
+        got: usize,
+        defaults: bool,
+    },
+    Missing(Vec<String>),
+    Unknown(String),
+    Duplicate(String),
+}
+
+impl ArgumentError {
+    // The checker quotes the name of the function, the interpreter doesn't.
+    pub fn message(&self, func_name: &str) -> String {
+        match self {
+            ArgumentError::TooMany {
+                max,
+                got,
+                defaults: false,
+            } => format!(
+                "Function {} takes {} arguments but got {}.",
+                func_name, max, got
+            ),
+            ArgumentError::TooMany { max, got, .. } => format!(
+                "Function {} takes at most {} arguments but got {}.",
+                func_name, max, got
+            ),
+            ArgumentError::Missing(names) => format!(
+                "Function {} is missing arguments for {}.",
+                func_name,
+                names.join(", ")
+            ),
+            ArgumentError::Unknown(name) => {
+                format!("Function {} has no parameter named {}.", func_name, name)
+            }
+            ArgumentError::Duplicate(name) => format!(
+                "Function {} got more than one value for {}.",
+                func_name, name
+            ),
+        }
+    }
+}
+
+// Gives every parameter its positional or keyword argument, None when its default value is
+// used. The extra positional arguments are returned for the rest parameter.
+pub fn match_arguments<T>(
+    decl: &FunctionDecl,
+    args: Vec<T>,
+    keywords: Vec<(String, T)>,
+) -> Result<(Vec<Option<T>>, Vec<T>), ArgumentError> {
+    if decl.rest.is_none() && args.len() > decl.params.len() {
+        return Err(ArgumentError::TooMany {
+            max: decl.params.len(),
+            got: args.len(),
+            defaults: decl.params.iter().any(|param| param.default.is_some()),
+        });
+    }
+
+    let mut args = args.into_iter();
+    let mut params: Vec<Option<T>> = decl.params.iter().map(|_| args.next()).collect();
+    for (name, value) in keywords {
+        let index = decl.params.iter().position(|param| param.name == name);
+        match index.map(|index| &mut params[index]) {
+            None => return Err(ArgumentError::Unknown(name)),
+            Some(Some(_)) => return Err(ArgumentError::Duplicate(name)),
+            Some(param) => *param = Some(value),
         }
     }
 
-    // Calls the function.
-    function_container.call_function(func.as_str(), args)
+    let missing: Vec<String> = decl
+        .params
+        .iter()
+        .zip(&params)
+        .filter(|(param, arg)| arg.is_none() && param.default.is_none())
+        .map(|(param, _)| param.name.clone())
+        .collect();
+    if !missing.is_empty() {
+        return Err(ArgumentError::Missing(missing));
+    }
+    Ok((params, args.collect()))
+}
+
+// Panics if the arguments don't fit the parameters of the function. The rest parameter gets
+// a list, it is None when the function has none.
+pub fn bind_arguments(
+    decl: &FunctionDecl,
+    args: Vec<VariableContent>,
+    keywords: Vec<(String, VariableContent)>,
+) -> (Vec<Option<VariableContent>>, Option<VariableContent>) {
+    let (params, rest) = match_arguments(decl, args, keywords)
+        .unwrap_or_else(|error| raise(ErrorKind::Argument, error.message(&decl.name)));
+    for (param, arg) in decl.params.iter().zip(&params) {
+        if let Some(arg) = arg {
+            check_argument(decl, param, arg.data_type);
+        }
+    }
+    let rest = decl.rest.as_ref().map(|_| VariableContent::list(rest));
+    (params, rest)
+}
+
+// Panics if the argument doesn't have the type of its parameter.
+pub fn check_argument(decl: &FunctionDecl, param: &Param, data_type: VariableTypes) {
+    if let Some(expected) = param.annotation {
+        if expected != data_type {
+            let message = format!(
+                "Argument {} of function {} should be of type {:?}, got {:?}.",
+                param.name, decl.name, expected, data_type
+            );
+            raise(ErrorKind::Type, message);
+        }
+    }
+}
+
+// Panics if the returned value doesn't match the return type of the function.
+pub fn check_return(decl: &FunctionDecl, data_type: VariableTypes) {
+    if let Some(expected) = decl.return_type {
+        if expected != data_type {
+            let message = format!(
+                "Function {} should return a value of type {:?}, got {:?}.",
+                decl.name, expected, data_type
+            );
+            raise(ErrorKind::Type, message);
+        }
+    }
 }
 
 // Functions for in the simple script source code.
-fn simple_print(args: Vec<VariableContent>) -> VariableContent {
+fn simple_print(args: Vec<VariableContent>, host: &mut Host) -> VariableContent {
     let mut final_message = String::new();
     for arg in args {
         final_message.push_str(arg.value.as_str());
     }
-    println!("{}", final_message);
-    io::stdout().flush().unwrap();
-    VariableContent {
-        data_type: VariableTypes::NULL,
-        value: "".to_string(),
-    }
+    final_message.push('\n');
+    host.write(&final_message);
+    VariableContent::null()
 }
 
 // Compares all variables to check if type and value are the same.
 fn simple_eq(args: Vec<VariableContent>) -> VariableContent {
     if args.len() < 2 {
-        panic!(
+        let message = format!(
             "Length of args was {}; there should atleast be 2 arguments.",
             args.len()
         );
+        raise(ErrorKind::Argument, message);
     }
 
-    // Gets value and type of the first argument.
-    let mut arg_iter = args.into_iter();
-    let eq_first = arg_iter.next().unwrap();
-    let eq_type = eq_first.data_type;
-    let eq_value = eq_first.value.clone();
+    // Loops through all arguments to check if they are equal to the first.
+    let equal = args[1..].iter().all(|arg| values_equal(&args[0], arg));
+    VariableContent::new(equal.to_string(), VariableTypes::BOOL)
+}
 
-    // Loops through all arguments to check if the value and type are equal to the first.
-    for arg in arg_iter {
-        if (arg.data_type != eq_type) || (arg.value != eq_value) {
-            return VariableContent {
-                value: "false".to_string(),
-                data_type: VariableTypes::BOOL,
-            };
-        }
+// Lists, structs and enums are equal when their items are, functions are only equal to themselves.
+pub fn values_equal(a: &VariableContent, b: &VariableContent) -> bool {
+    if a.data_type != b.data_type || a.value != b.value {
+        return false;
     }
-
-    VariableContent {
-        value: "true".to_string(),
-        data_type: VariableTypes::BOOL,
+    match (a.object.as_deref(), b.object.as_deref()) {
+        (Some(Object::List(a)), Some(Object::List(b)))
+        | (Some(Object::Struct { fields: a, .. }), Some(Object::Struct { fields: b, .. }))
+        | (Some(Object::Enum { payload: a, .. }), Some(Object::Enum { payload: b, .. })) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| values_equal(a, b))
+        }
+        (Some(a), Some(b)) => std::ptr::eq(a, b),
+        (a, b) => a.is_none() && b.is_none(),
     }
 }
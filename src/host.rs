@@ -0,0 +1,183 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Component, Path, PathBuf};
+
+use crate::buildin_functions::random_functions::Rng;
+use crate::clock::{Clock, SystemClock};
+use crate::errors::{raise, ErrorKind};
+
+// What the builtins reaching outside of the script go through, the embedding code decides
+// where that is.
+pub struct Host {
+    pub root: PathBuf,           // The only directory the file builtins can use.
+    pub input: Box<dyn BufRead>, // Where the input builtins read from, stdin by default.
+    pub output: Box<dyn Write>,  // Where print, printf and prompts go, stdout by default.
+    pub rng: Rng,                // A seed gives the same numbers every run.
+    pub clock: Box<dyn Clock>,   // The time builtins read it, a fake clock makes them repeatable.
+    pub deadline: Option<i64>,   // The monotonic time of the clock a time limit ends at.
+}
+
+impl Host {
+    pub fn new() -> Host {
+        Host {
+            root: PathBuf::from("."),
+            input: Box::new(BufReader::new(io::stdin())),
+            output: Box::new(io::stdout()),
+            rng: Rng::from_time(),
+            clock: Box::new(SystemClock::new()),
+            deadline: None,
+        }
+    }
+
+    // The next line without its line ending, None at the end of the input.
+    pub fn read_line(&mut self) -> Option<String> {
+        let mut line = String::new();
+        match self.input.read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => {
+                if line.ends_with('\n') {
+                    line.pop();
+                    if line.ends_with('\r') {
+                        line.pop();
+                    }
+                }
+                Some(line)
+            }
+            Err(error) => raise(
+                ErrorKind::Io,
+                format!("Couldn't read the input: {}.", error),
+            ),
+        }
+    }
+
+    // The rest of the input.
+    pub fn read_all(&mut self) -> String {
+        let mut text = String::new();
+        if let Err(error) = self.input.read_to_string(&mut text) {
+            raise(
+                ErrorKind::Io,
+                format!("Couldn't read the input: {}.", error),
+            );
+        }
+        text
+    }
+
+    // Flushes every write, so a prompt shows before the input is read.
+    pub fn write(&mut self, text: &str) {
+        let written = self.output.write_all(text.as_bytes());
+        if let Err(error) = written.and_then(|_| self.output.flush()) {
+            raise(
+                ErrorKind::Io,
+                format!("Couldn't write the output: {}.", error),
+            );
+        }
+    }
+
+    // Finds the path inside of the root, paths leaving it through `..` or a symlink are
+    // refused. The part of the path that doesn't exist yet is checked without symlinks.
+    pub fn path(&self, path: &str) -> PathBuf {
+        let root = fs::canonicalize(&self.root).unwrap_or_else(|error| {
+            let message = format!(
+                "Couldn't open the root directory '{}': {}.",
+                self.root.display(),
+                error
+            );
+            raise(ErrorKind::Io, message)
+        });
+
+        let mut inside = PathBuf::new();
+        for component in Path::new(path).components() {
+            match component {
+                Component::Normal(part) => inside.push(part),
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    if !inside.pop() {
+                        outside(path);
+                    }
+                }
+                Component::RootDir | Component::Prefix(_) => outside(path),
+            }
+        }
+
+        let full = root.join(&inside);
+        let mut existing = full.as_path();
+        while fs::symlink_metadata(existing).is_err() {
+            existing = existing.parent().unwrap_or(&root);
+        }
+        let resolved = fs::canonicalize(existing).unwrap_or_else(|_| outside(path));
+        if !resolved.starts_with(&root) {
+            outside(path);
+        }
+        match full.strip_prefix(existing) {
+            Ok(rest) if rest != Path::new("") => resolved.join(rest),
+            _ => resolved,
+        }
+    }
+}
+
+impl Default for Host {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn outside(path: &str) -> ! {
+    let message = format!("Path '{}' is outside of the root directory.", path);
+    raise(ErrorKind::Permission, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::catch_error;
+    use std::os::unix::fs::symlink;
+    use std::process;
+
+    // A root with a directory sub, a directory outside of it and the links the test makes.
+    fn make_root(name: &str) -> (Host, PathBuf, PathBuf) {
+        let base = std::env::temp_dir().join(format!("simple_script_{}_{}", name, process::id()));
+        let root = base.join("root");
+        let outside = base.join("outside");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        let host = Host {
+            root: root.clone(),
+            ..Host::new()
+        };
+        (host, fs::canonicalize(root).unwrap(), outside)
+    }
+
+    fn refused(host: &Host, path: &str) -> bool {
+        match catch_error(|| host.path(path)) {
+            Ok(_) => false,
+            Err(error) => error.kind == ErrorKind::Permission,
+        }
+    }
+
+    #[test]
+    fn paths_stay_in_the_root() {
+        let (host, root, outside) = make_root("paths");
+        assert_eq!(host.path("sub/../file.txt"), root.join("file.txt"));
+        assert_eq!(host.path("./new/file.txt"), root.join("new/file.txt"));
+        assert!(refused(&host, ".."));
+        assert!(refused(&host, "../outside/file.txt"));
+        assert!(refused(&host, "sub/../../file.txt"));
+        assert!(refused(&host, "/etc/passwd"));
+        assert!(refused(&host, outside.to_str().unwrap()));
+        fs::remove_dir_all(root.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn symlinks_only_lead_inside_of_the_root() {
+        let (host, root, outside) = make_root("symlinks");
+        symlink(root.join("sub"), root.join("inner")).unwrap();
+        symlink(&outside, root.join("escape")).unwrap();
+        symlink(outside.join("missing"), root.join("dangling")).unwrap();
+        assert_eq!(host.path("inner/file.txt"), root.join("sub/file.txt"));
+        assert!(refused(&host, "escape"));
+        assert!(refused(&host, "escape/file.txt"));
+        assert!(refused(&host, "dangling"));
+        assert!(refused(&host, "dangling/file.txt"));
+        fs::remove_dir_all(root.parent().unwrap()).unwrap();
+    }
+}
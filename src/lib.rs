@@ -0,0 +1,26 @@
+extern crate pest;
+#[macro_use]
+extern crate pest_derive;
+pub mod ast;
+pub mod buildin_functions;
+pub mod bytecode;
+pub mod capabilities;
+pub mod checker;
+pub mod clock;
+pub mod control_flow;
+pub mod enums;
+pub mod errors;
+pub mod execute_code;
+pub mod functions;
+pub mod host;
+pub mod limits;
+pub mod modules;
+pub mod optimizer;
+pub mod resolver;
+pub mod structs;
+pub mod type_string;
+pub mod variables;
+
+#[derive(Parser)]
+#[grammar = "grammar.pest"] // Relative to src.
+pub struct SimpleParser;
@@ -0,0 +1,235 @@
+use std::cell::Cell;
+use std::time::Duration;
+
+use crate::clock::Clock;
+use crate::errors::{raise, ErrorKind};
+use crate::variables::VariableContent;
+
+// Deep enough for scripts that recurse a bit, but the executor recurses on the stack of Rust
+// for every call so it stops before a stack of 8 MB overflows.
+pub const DEFAULT_CALL_DEPTH: usize = 500;
+
+// The most calls the executors go, whatever the limits say. Main runs them on a stack of
+// 256 MB, which holds about 25000 calls of the executor. Embedding code running the executor
+// on a smaller stack keeps the call depth lower, about 500 for every 8 MB.
+pub const MAX_CALL_DEPTH: usize = 10_000;
+
+// The statements or instructions that still run after the instructions or the time ran out,
+// so a catch block can handle the error before the script is stopped for good.
+pub const CATCH_GRACE: u64 = 1000;
+
+thread_local! {
+    // The bytes the running script can still take and its limit, the builtins check it before
+    // making a value.
+    static MEMORY_LEFT: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+// What a script is allowed to use, so scripts of users can't run forever or take all the
+// memory. None is no limit.
+#[derive(Debug, Clone)]
+pub struct Limits {
+    pub instructions: Option<u64>, // Statements for the executor, instructions for the VM.
+    pub call_depth: Option<usize>, // Calls of functions and imports of files running at once.
+    pub scope_depth: Option<usize>, // Only the executor keeps scopes while running.
+    pub memory: Option<usize>,     // The bytes of the strings and collections held at once.
+    pub time: Option<Duration>,
+}
+
+impl Limits {
+    pub fn new() -> Limits {
+        Limits {
+            instructions: None,
+            call_depth: Some(DEFAULT_CALL_DEPTH),
+            scope_depth: None,
+            memory: None,
+            time: None,
+        }
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The bytes a value takes. The value of a collection is how print shows it, and the values
+// inside of it keep how they are shown too, so nested lists count every level.
+pub fn size(value: &VariableContent) -> usize {
+    value.value.len().saturating_add(value.nested)
+}
+
+// Raises before a builtin makes a value of that many bytes when the script can't hold them.
+pub fn check_memory(bytes: usize) {
+    if let Some((left, max)) = MEMORY_LEFT.with(Cell::get) {
+        if bytes > left {
+            let message = format!(
+                "A value of {} bytes doesn't fit in the memory limit of {} bytes.",
+                bytes, max
+            );
+            raise(ErrorKind::MemoryLimit, message);
+        }
+    }
+}
+
+// Runs the function with only that much memory for the builtins, the memory of the script
+// running before is restored afterwards even when it raised.
+pub fn with_memory_left<T>(left: Option<usize>, function: impl FnOnce() -> T) -> T {
+    struct Restore(Option<(usize, usize)>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            MEMORY_LEFT.with(|memory| memory.set(self.0));
+        }
+    }
+    let left = left.map(|left| (left, left));
+    let _restore = Restore(MEMORY_LEFT.with(|memory| memory.replace(left)));
+    function()
+}
+
+// How much of the limits a running script used. Once the instructions or the time run out
+// the script gets CATCH_GRACE more steps, after those every step raises the error again.
+pub struct Budget {
+    pub limits: Limits,
+    pub depth: usize, // The calls running, the executors restore it when they catch an error.
+    pub held: usize,  // The bytes of the variables of the calls below the running one.
+    steps: u64,
+    started: i64, // The monotonic time of the clock of the host.
+    exceeded: Option<(ErrorKind, String)>,
+    grace: u64,
+}
+
+impl Budget {
+    pub fn new(limits: Limits) -> Budget {
+        Budget {
+            limits,
+            depth: 0,
+            held: 0,
+            steps: 0,
+            started: 0,
+            exceeded: None,
+            grace: 0,
+        }
+    }
+
+    // The time is counted from the start of the script.
+    pub fn start(&mut self, clock: &dyn Clock) {
+        self.steps = 0;
+        self.held = 0;
+        self.started = clock.monotonic();
+        self.exceeded = None;
+        let memory = self.limits.memory.map(|max| (max, max));
+        MEMORY_LEFT.with(|left| left.set(memory));
+    }
+
+    // The monotonic time the script has to end by, sleeping stops there.
+    pub fn deadline(&self) -> Option<i64> {
+        let time = self.limits.time?;
+        Some(self.started.saturating_add(time.as_millis() as i64))
+    }
+
+    // Counts a statement or an instruction, the clock is only read every so often.
+    pub fn step(&mut self, clock: &dyn Clock) {
+        self.steps += 1;
+        if let Some((kind, message)) = &self.exceeded {
+            if self.grace == 0 {
+                raise(*kind, message.clone());
+            }
+            self.grace -= 1;
+            return;
+        }
+        if let Some(max) = self.limits.instructions {
+            if self.steps > max {
+                let message = format!("The script ran more than {} instructions.", max);
+                self.exceed(ErrorKind::InstructionLimit, message);
+            }
+        }
+        if self.steps.is_multiple_of(64) {
+            self.check_time(clock);
+        }
+    }
+
+    // Reads the clock now, the executors call it after builtins that can take a while.
+    pub fn check_time(&mut self, clock: &dyn Clock) {
+        if self.exceeded.is_some() {
+            return;
+        }
+        if let Some(deadline) = self.deadline() {
+            if clock.monotonic() > deadline {
+                let time = self.limits.time.unwrap().as_millis();
+                let message = format!("The script ran longer than {} ms.", time);
+                self.exceed(ErrorKind::TimeLimit, message);
+            }
+        }
+    }
+
+    fn exceed(&mut self, kind: ErrorKind, message: String) -> ! {
+        self.exceeded = Some((kind, message.clone()));
+        self.grace = CATCH_GRACE;
+        raise(kind, message)
+    }
+
+    // Raises when another call can't start with that many calls running.
+    pub fn check_depth(&self, depth: usize) {
+        let max = self
+            .limits
+            .call_depth
+            .unwrap_or(MAX_CALL_DEPTH)
+            .min(MAX_CALL_DEPTH);
+        if depth >= max {
+            let message = format!("The script went more than {} calls deep.", max);
+            raise(ErrorKind::CallDepth, message);
+        }
+    }
+
+    // The caller holds the bytes of its variables while the call runs.
+    pub fn call_in(&mut self, held: usize) {
+        self.check_depth(self.depth);
+        self.depth += 1;
+        self.held += held;
+    }
+
+    pub fn call_out(&mut self, held: usize) {
+        self.depth -= 1;
+        self.held -= held;
+    }
+
+    // Only counted with a memory limit, as counting goes through every variable.
+    pub fn counts_memory(&self) -> bool {
+        self.limits.memory.is_some()
+    }
+
+    // The running total of the bytes held, with the variables of the running code. What is
+    // left is what the builtins can still make.
+    pub fn count_memory(&self, used: usize) {
+        let max = match self.limits.memory {
+            Some(max) => max,
+            None => return,
+        };
+        let total = self.held.saturating_add(used);
+        if total > max {
+            let message = format!(
+                "The script holds {} bytes, over the memory limit of {} bytes.",
+                total, max
+            );
+            raise(ErrorKind::MemoryLimit, message);
+        }
+        MEMORY_LEFT.with(|memory| memory.set(Some((max - total, max))));
+    }
+
+    // A single value can't take more than all of the memory.
+    pub fn check_size(&self, value: &VariableContent) {
+        let max = match self.limits.memory {
+            Some(max) => max,
+            None => return,
+        };
+        if size(value) > max {
+            let message = format!(
+                "A {:?} of {} bytes is over the memory limit of {} bytes.",
+                value.data_type,
+                size(value),
+                max
+            );
+            raise(ErrorKind::MemoryLimit, message);
+        }
+    }
+}
@@ -1,45 +1,140 @@
-extern crate pest;
-#[macro_use]
-extern crate pest_derive;
-pub mod buildin_functions;
-pub mod control_flow;
-pub mod execute_code;
-pub mod functions;
-pub mod type_string;
-pub mod variables;
+use std::env;
+use std::process;
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
 
-use std::fs;
+use simple_script::ast::format_program;
+use simple_script::buildin_functions::random_functions::Rng;
+use simple_script::bytecode::compiler::compile_program;
+use simple_script::bytecode::vm::VirtualMachine;
+use simple_script::capabilities::Capabilities;
+use simple_script::checker::check_program;
+use simple_script::clock::FakeClock;
+use simple_script::execute_code::CodeExecutor;
+use simple_script::functions::FunctionContainer;
+use simple_script::host::Host;
+use simple_script::limits::Limits;
+use simple_script::modules::{load_modules, Module};
+use simple_script::optimizer::optimize_program;
+use simple_script::resolver::resolve_program;
 
-use pest::Parser;
-
-use crate::execute_code::CodeExecutor;
-
-#[derive(Parser)]
-#[grammar = "grammar.pest"] // Relative to src.
-struct SimpleParser;
+// The executor recurses on the stack of Rust for every call, limits::MAX_CALL_DEPTH calls of
+// it fit in a stack this big. Only the part that is used is taken from the memory.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
 
 fn main() {
-    // Getting the source code of the user.
-    let mut file_content = fs::read_to_string("main.smpl").expect("Couldn't read the file.");
-
-    // Preventing nasty error where parser can't reach EOI because there is no newline; now there is a newline.
-    file_content.push('\n');
+    let runner = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)
+        .expect("Couldn't start the thread running the script.");
+    if runner.join().is_err() {
+        process::exit(101);
+    }
+}
 
-    // Parse the file.
-    let parsed = SimpleParser::parse(Rule::ast, &file_content).expect("Unable to parse file.");
+fn run() {
+    // Reads the flags and the file to run, which is main.smpl by default.
+    let mut check_only = false;
+    let mut use_vm = false;
+    let mut dump_optimized = false;
+    let mut limits = Limits::new();
+    let mut capabilities = Capabilities::all();
+    let mut host = Host::new();
+    let mut file_name = "main.smpl".to_string();
+    for arg in env::args().skip(1) {
+        let (flag, value) = arg.split_once('=').unwrap_or((&arg, ""));
+        match flag {
+            "--check" => check_only = true,
+            "--vm" => use_vm = true,
+            "--dump-optimized" => dump_optimized = true,
+            "--max-instructions" => limits.instructions = Some(limit(flag, value)),
+            "--max-call-depth" => limits.call_depth = Some(limit(flag, value)),
+            "--max-scope-depth" => limits.scope_depth = Some(limit(flag, value)),
+            "--max-memory" => limits.memory = Some(limit(flag, value)),
+            "--timeout" => limits.time = Some(Duration::from_millis(limit(flag, value))),
+            "--root" => host.root = value.into(),
+            "--seed" => host.rng = Rng::new(limit(flag, value)),
+            "--fake-clock" => host.clock = Box::new(FakeClock::new(limit(flag, value))),
+            "--allow" => {
+                capabilities = Capabilities::from_names(value).unwrap_or_else(|error| {
+                    eprintln!("{}", error);
+                    process::exit(2);
+                })
+            }
+            _ if arg.starts_with("--") => {
+                eprintln!("Unknown flag '{}'.", arg);
+                process::exit(2);
+            }
+            _ => file_name = arg,
+        }
+    }
 
-    // Init container.
-    let mut code_executor = CodeExecutor::new();
+    // Parses the file and the files it imports into statements.
+    let mut modules = load_modules(&file_name);
 
-    // Loop through the declerations.
-    for pair in parsed {
-        match pair.as_rule() {
-            Rule::EOI => {}
-            Rule::line => code_executor.execute_code(pair.into_inner()),
-            _ => panic!("Unimplemented rule '{:?}'.", pair.as_rule()),
+    // Refuses to run code with mistakes that can be found up front, --check also looks at the types.
+    // Resolving also binds the variables to their slots for the executor.
+    let mut failed = false;
+    for module in &mut modules {
+        let mut errors = std::mem::take(&mut module.errors);
+        errors.extend(resolve_program(&module.program, &module.source));
+        errors.extend(check_program(&module.program, &module.source, check_only));
+        errors.sort_by_key(|error| (error.line, error.column));
+        for error in &errors {
+            eprintln!("{}:{}", module.path, error);
         }
+        failed |= errors.iter().any(|error| !error.warning);
+    }
+    if failed {
+        process::exit(1);
+    }
+    if check_only {
+        return;
+    }
+
+    // The optimized program gets new slots, as flattening an if removes a scope.
+    let modules: Vec<Module> = modules
+        .into_iter()
+        .map(|module| {
+            let program = optimize_program(&module.program, &FunctionContainer::new());
+            resolve_program(&program, &module.source);
+            Module { program, ..module }
+        })
+        .collect();
+    if dump_optimized {
+        print!("{}", format_program(&modules[0].program));
+        return;
+    }
+
+    // Runs the program with the bytecode VM or by walking the statements.
+    let result = if use_vm {
+        let builtins = FunctionContainer::with_capabilities(capabilities);
+        let compiled = compile_program(&modules, &builtins);
+        let mut vm = VirtualMachine::new(&compiled);
+        vm.set_limits(limits);
+        vm.set_host(host);
+        vm.run()
+    } else {
+        let mut executor = CodeExecutor::new();
+        executor.function_container = FunctionContainer::with_capabilities(capabilities);
+        executor.set_limits(limits);
+        executor.host = host;
+        executor.run(modules.into_iter().map(Rc::new).collect())
+    };
+    if let Err(error) = result {
+        eprintln!("{}", error);
+        process::exit(1);
     }
 
     // Prints out the variables at the end of a program.
     // code_executor.var_container.debug_print_vars();
 }
+
+// The number of a limit flag like --max-memory=1000000.
+fn limit<T: std::str::FromStr>(flag: &str, value: &str) -> T {
+    value.parse().unwrap_or_else(|_| {
+        eprintln!("Flag '{}' needs a number like {}=1000.", flag, flag);
+        process::exit(2);
+    })
+}
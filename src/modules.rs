@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use pest::Parser;
+
+use crate::ast::{build_ast, FunctionDecl, Statement};
+use crate::checker::CheckError;
+use crate::{Rule, SimpleParser};
+
+// A file of the program, the first one is the file that is run and the others are imported.
+pub struct Module {
+    pub path: String, // Found from the path of the file importing it, errors are shown with it.
+    pub source: String,
+    pub program: Vec<Statement>,
+    pub uses: Vec<String>, // The builtin modules its calls are looked up in.
+    pub errors: Vec<CheckError>, // The imports that couldn't be loaded.
+}
+
+// Loads the file and every file it imports, a file imported by several files is only loaded
+// once. Every import gets the index of its file.
+pub fn load_modules(path: &str) -> Vec<Module> {
+    let source = fs::read_to_string(path).expect("Couldn't read the file.");
+    let canonical = fs::canonicalize(path).expect("Couldn't read the file.");
+    let mut loader = Loader {
+        modules: Vec::new(),
+        indexes: HashMap::new(),
+        loading: Vec::new(),
+    };
+    loader.load(path.to_string(), canonical, source);
+    loader.modules
+}
+
+struct Loader {
+    modules: Vec<Module>,
+    indexes: HashMap<PathBuf, usize>, // By the canonical path, so the same file is found.
+    loading: Vec<(PathBuf, String)>,  // The file being loaded and the files importing it.
+}
+
+impl Loader {
+    fn load(&mut self, path: String, canonical: PathBuf, source: String) -> usize {
+        let parsed = SimpleParser::parse(Rule::ast, &source)
+            .unwrap_or_else(|error| panic!("Unable to parse {}.\n{}", path, error));
+        let program = build_ast(parsed);
+
+        // The files it imports come after it.
+        let index = self.modules.len();
+        self.modules.push(Module {
+            path: path.clone(),
+            source: String::new(),
+            program: Vec::new(),
+            uses: Vec::new(),
+            errors: Vec::new(),
+        });
+        self.indexes.insert(canonical.clone(), index);
+        self.loading.push((canonical, path.clone()));
+        let mut errors = Vec::new();
+        for statement in &program {
+            if let Statement::Import {
+                path: import,
+                module,
+                span,
+                ..
+            } = statement
+            {
+                match self.import(&path, import) {
+                    Ok(imported) => module.set(Some(imported)),
+                    Err(message) => errors.push(CheckError::new(*span, &source, message)),
+                }
+            }
+        }
+        self.loading.pop();
+
+        let module = &mut self.modules[index];
+        module.source = source;
+        module.uses = uses(&program);
+        module.program = program;
+        module.errors = errors;
+        index
+    }
+
+    // Imports are found from the directory of the importing file.
+    fn import(&mut self, importer: &str, import: &str) -> Result<usize, String> {
+        let path = Path::new(importer)
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(import);
+        let shown = path.to_string_lossy().to_string();
+        let canonical = fs::canonicalize(&path)
+            .map_err(|_| format!("Couldn't find the file '{}' to import.", shown))?;
+
+        // The chain of imports ends with the file it started from.
+        if let Some(start) = self.loading.iter().position(|(file, _)| *file == canonical) {
+            let mut chain: Vec<&str> = self.loading[start..]
+                .iter()
+                .map(|(_, path)| path.as_str())
+                .collect();
+            chain.push(chain[0]);
+            return Err(format!("Circular import: {}.", chain.join(" -> ")));
+        }
+        if let Some(index) = self.indexes.get(&canonical) {
+            return Ok(*index);
+        }
+        let source = fs::read_to_string(&path)
+            .map_err(|_| format!("Couldn't read the file '{}' to import.", shown))?;
+        Ok(self.load(shown, canonical, source))
+    }
+}
+
+// What a file gives the files importing it.
+pub enum Export<'a> {
+    Function(&'a Rc<FunctionDecl>),
+    Constant(usize), // The index of the global.
+}
+
+// The top-level functions and constants of a file, a function declared again replaces the
+// first one.
+pub fn exports(program: &[Statement]) -> Vec<(&str, Export<'_>)> {
+    let mut exports: Vec<(&str, Export)> = Vec::new();
+    for statement in program {
+        let export = match statement {
+            Statement::FunctionDecl(decl) => (decl.name.as_str(), Export::Function(decl)),
+            Statement::VarDecl {
+                name,
+                constant: true,
+                index,
+                ..
+            } => {
+                let index = index.get().expect("Variable wasn't resolved.");
+                (name.as_str(), Export::Constant(index))
+            }
+            _ => continue,
+        };
+        exports.retain(|(name, _)| *name != export.0);
+        exports.push(export);
+    }
+    exports
+}
+
+// The builtin modules named by the use statements of a file, in order. They apply to the whole
+// file, like its imports.
+pub fn uses(program: &[Statement]) -> Vec<String> {
+    program
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::Use { module, .. } => Some(module.clone()),
+            _ => None,
+        })
+        .collect()
+}
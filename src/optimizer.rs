@@ -0,0 +1,425 @@
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use crate::ast::{
+    CatchClause, Expression, FieldValue, FunctionDecl, KeywordArg, MatchArm, Param, Statement,
+};
+use crate::errors::catch_error;
+use crate::functions::{Builtin, FunctionContainer};
+use crate::limits::{size, with_memory_left};
+use crate::modules::uses;
+use crate::variables::{VariableContent, VariableTypes};
+
+// The most bytes a folded value takes, bigger ones are made when the program runs, where the
+// limits of the script apply.
+const FOLD_LIMIT: usize = 4096;
+
+// Folds calls to pure builtins that only get literals, removes ifs that can never run and
+// flattens the ones that always run. The program has to be resolved again afterwards.
+pub fn optimize_program(program: &[Statement], builtins: &FunctionContainer) -> Vec<Statement> {
+    let mut optimizer = Optimizer {
+        builtins,
+        user_functions: HashSet::new(),
+        uses: uses(program),
+    };
+    optimizer.collect_functions(program);
+    optimizer.optimize_statements(program)
+}
+
+struct Optimizer<'a> {
+    builtins: &'a FunctionContainer,
+    user_functions: HashSet<String>, // Declaring one with the name of a builtin fails at runtime.
+    uses: Vec<String>,
+}
+
+impl Optimizer<'_> {
+    // Functions can be declared in any body, also in the arms of a match and in function literals.
+    fn collect_functions(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            match statement {
+                Statement::FunctionDecl(decl) => {
+                    self.user_functions.insert(decl.name.clone());
+                    self.collect_in_function(decl);
+                }
+                Statement::VarDecl {
+                    value: Some(value), ..
+                }
+                | Statement::Return {
+                    value: Some(value), ..
+                }
+                | Statement::Assign { value, .. }
+                | Statement::FieldAssign { value, .. }
+                | Statement::Throw { value, .. }
+                | Statement::Expression(value) => self.collect_in_expression(value),
+                Statement::If {
+                    condition, body, ..
+                } => {
+                    self.collect_in_expression(condition);
+                    self.collect_functions(body);
+                }
+                Statement::Match { value, arms, .. } => {
+                    self.collect_in_expression(value);
+                    for arm in arms {
+                        self.collect_functions(&arm.body);
+                    }
+                }
+                Statement::Try {
+                    body,
+                    catch,
+                    finally,
+                    ..
+                } => {
+                    self.collect_functions(body);
+                    if let Some(catch) = catch {
+                        self.collect_functions(&catch.body);
+                    }
+                    if let Some(finally) = finally {
+                        self.collect_functions(finally);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn collect_in_function(&mut self, decl: &FunctionDecl) {
+        for param in &decl.params {
+            if let Some(default) = &param.default {
+                self.collect_in_expression(default);
+            }
+        }
+        self.collect_functions(&decl.body);
+    }
+
+    fn collect_in_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Function(decl) => self.collect_in_function(decl),
+            Expression::Call { args, keywords, .. } => {
+                for arg in args {
+                    self.collect_in_expression(arg);
+                }
+                for keyword in keywords {
+                    self.collect_in_expression(&keyword.value);
+                }
+            }
+            Expression::List { items: args, .. } | Expression::Variant { args, .. } => {
+                for arg in args {
+                    self.collect_in_expression(arg);
+                }
+            }
+            Expression::Struct { fields, .. } => {
+                for field in fields {
+                    self.collect_in_expression(&field.value);
+                }
+            }
+            Expression::Field { value, .. } => self.collect_in_expression(value),
+            Expression::Literal { .. } | Expression::Variable { .. } => {}
+        }
+    }
+
+    fn optimize_statements(&mut self, statements: &[Statement]) -> Vec<Statement> {
+        let mut optimized = Vec::new();
+        for statement in statements {
+            self.optimize_statement(statement, &mut optimized);
+        }
+        optimized
+    }
+
+    fn optimize_statement(&mut self, statement: &Statement, optimized: &mut Vec<Statement>) {
+        let statement = match statement {
+            Statement::VarDecl {
+                name,
+                annotation,
+                constant,
+                value,
+                index,
+                span,
+            } => Statement::VarDecl {
+                name: name.clone(),
+                annotation: *annotation,
+                constant: *constant,
+                value: value.as_ref().map(|value| self.optimize_expression(value)),
+                index: index.clone(),
+                span: *span,
+            },
+            Statement::Assign {
+                name,
+                value,
+                slot,
+                span,
+            } => Statement::Assign {
+                name: name.clone(),
+                value: self.optimize_expression(value),
+                slot: slot.clone(),
+                span: *span,
+            },
+            Statement::FieldAssign {
+                name,
+                path,
+                value,
+                slot,
+                span,
+            } => Statement::FieldAssign {
+                name: name.clone(),
+                path: path.clone(),
+                value: self.optimize_expression(value),
+                slot: slot.clone(),
+                span: *span,
+            },
+            Statement::FunctionDecl(decl) => Statement::FunctionDecl(self.optimize_function(decl)),
+            Statement::StructDecl(decl) => Statement::StructDecl(decl.clone()),
+            Statement::EnumDecl(decl) => Statement::EnumDecl(decl.clone()),
+            Statement::Match { value, arms, span } => Statement::Match {
+                value: self.optimize_expression(value),
+                arms: arms
+                    .iter()
+                    .map(|arm| MatchArm {
+                        pattern: arm.pattern.clone(),
+                        body: self.optimize_statements(&arm.body),
+                        span: arm.span,
+                    })
+                    .collect(),
+                span: *span,
+            },
+            Statement::Return { value, span } => Statement::Return {
+                value: value.as_ref().map(|value| self.optimize_expression(value)),
+                span: *span,
+            },
+            Statement::If {
+                condition,
+                body,
+                span,
+            } => {
+                // The body of an if that never runs isn't folded at all.
+                let condition = self.optimize_expression(condition);
+                if literal_bool(&condition) == Some(false) {
+                    return;
+                }
+                let body = self.optimize_statements(body);
+                match literal_bool(&condition) {
+                    // The body gets its own scope, so it is only flattened when that doesn't matter.
+                    Some(true) if !needs_scope(&body) => {
+                        optimized.extend(body);
+                        return;
+                    }
+                    _ => Statement::If {
+                        condition,
+                        body,
+                        span: *span,
+                    },
+                }
+            }
+            Statement::Throw { value, span } => Statement::Throw {
+                value: self.optimize_expression(value),
+                span: *span,
+            },
+            Statement::Try {
+                body,
+                catch,
+                finally,
+                span,
+            } => Statement::Try {
+                body: self.optimize_statements(body),
+                catch: catch.as_ref().map(|catch| CatchClause {
+                    name: catch.name.clone(),
+                    body: self.optimize_statements(&catch.body),
+                    span: catch.span,
+                }),
+                finally: finally
+                    .as_ref()
+                    .map(|finally| self.optimize_statements(finally)),
+                span: *span,
+            },
+            Statement::Expression(expression) => {
+                Statement::Expression(self.optimize_expression(expression))
+            }
+            Statement::Import {
+                path,
+                name,
+                module,
+                index,
+                span,
+            } => Statement::Import {
+                path: path.clone(),
+                name: name.clone(),
+                module: module.clone(),
+                index: index.clone(),
+                span: *span,
+            },
+            Statement::Use { module, span } => Statement::Use {
+                module: module.clone(),
+                span: *span,
+            },
+            Statement::DebugVar { span } => Statement::DebugVar { span: *span },
+        };
+        optimized.push(statement);
+    }
+
+    fn optimize_function(&mut self, decl: &FunctionDecl) -> Rc<FunctionDecl> {
+        Rc::new(FunctionDecl {
+            name: decl.name.clone(),
+            params: decl
+                .params
+                .iter()
+                .map(|param| Param {
+                    name: param.name.clone(),
+                    annotation: param.annotation,
+                    default: param
+                        .default
+                        .as_ref()
+                        .map(|default| self.optimize_expression(default)),
+                })
+                .collect(),
+            rest: decl.rest.clone(),
+            return_type: decl.return_type,
+            body: self.optimize_statements(&decl.body),
+            span: decl.span,
+        })
+    }
+
+    fn optimize_expression(&mut self, expression: &Expression) -> Expression {
+        match expression {
+            Expression::Call {
+                namespace,
+                name,
+                args,
+                keywords,
+                slot,
+                span,
+            } => {
+                let args: Vec<Expression> = args
+                    .iter()
+                    .map(|arg| self.optimize_expression(arg))
+                    .collect();
+                let keywords: Vec<KeywordArg> = keywords
+                    .iter()
+                    .map(|keyword| KeywordArg {
+                        name: keyword.name.clone(),
+                        value: self.optimize_expression(&keyword.value),
+                        span: keyword.span,
+                    })
+                    .collect();
+                // Calls to a variable holding a function or to an imported module are left
+                // alone, builtins don't take keyword arguments.
+                let folded = match (slot.get(), namespace) {
+                    (None, Some(namespace)) if keywords.is_empty() => {
+                        self.fold_call(&format!("{}.{}", namespace, name), &args)
+                    }
+                    (None, None) if keywords.is_empty() => self.fold_call(name, &args),
+                    _ => None,
+                };
+                match folded {
+                    Some(value) => Expression::Literal { value, span: *span },
+                    None => Expression::Call {
+                        namespace: namespace.clone(),
+                        name: name.clone(),
+                        args,
+                        keywords,
+                        slot: slot.clone(),
+                        span: *span,
+                    },
+                }
+            }
+            Expression::Function(decl) => Expression::Function(self.optimize_function(decl)),
+            Expression::Struct {
+                name,
+                fields,
+                decl,
+                span,
+            } => Expression::Struct {
+                name: name.clone(),
+                fields: fields
+                    .iter()
+                    .map(|field| FieldValue {
+                        name: field.name.clone(),
+                        value: self.optimize_expression(&field.value),
+                        span: field.span,
+                    })
+                    .collect(),
+                decl: decl.clone(),
+                span: *span,
+            },
+            Expression::Variant {
+                enum_name,
+                variant,
+                args,
+                decl,
+                span,
+            } => Expression::Variant {
+                enum_name: enum_name.clone(),
+                variant: variant.clone(),
+                args: args
+                    .iter()
+                    .map(|arg| self.optimize_expression(arg))
+                    .collect(),
+                decl: decl.clone(),
+                span: *span,
+            },
+            Expression::List { items, span } => Expression::List {
+                items: items
+                    .iter()
+                    .map(|item| self.optimize_expression(item))
+                    .collect(),
+                span: *span,
+            },
+            _ => expression.clone(),
+        }
+    }
+
+    // Only calls the builtin when the arguments fit its signature, a call that still fails is
+    // left to raise its error when it runs.
+    fn fold_call(&self, name: &str, args: &[Expression]) -> Option<VariableContent> {
+        if self.user_functions.contains(name) {
+            return None;
+        }
+        let name = &self.builtins.builtin_name(name, &self.uses)?;
+        let signature = self.builtins.get_signature(name)?;
+        if !signature.pure || args.len() < signature.min_args {
+            return None;
+        }
+
+        let mut values = Vec::new();
+        for arg in args {
+            match arg {
+                Expression::Literal { value, .. } => values.push(value.clone()),
+                _ => return None,
+            }
+        }
+        if let Some(allowed) = &signature.arg_types {
+            if values
+                .iter()
+                .any(|value| !allowed.contains(&value.data_type))
+            {
+                return None;
+            }
+        }
+        match self.builtins.get_builtin(name)? {
+            // An error, like a value too big for the fold limit, leaves the call as it is.
+            Builtin::Simple(function) => {
+                catch_error(|| with_memory_left(Some(FOLD_LIMIT), || function(values)))
+                    .ok()
+                    .filter(|value| size(value) <= FOLD_LIMIT)
+            }
+            Builtin::HigherOrder(_) | Builtin::Host(_) | Builtin::Denied(_) => None,
+        }
+    }
+}
+
+fn literal_bool(expression: &Expression) -> Option<bool> {
+    match expression {
+        Expression::Literal { value, .. } if value.data_type == VariableTypes::BOOL => {
+            Some(value.value == "true")
+        }
+        _ => None,
+    }
+}
+
+// Declarations and debug.var depend on the scope they are in.
+fn needs_scope(body: &[Statement]) -> bool {
+    body.iter().any(|statement| {
+        matches!(
+            statement,
+            Statement::VarDecl { .. } | Statement::Import { .. } | Statement::DebugVar { .. }
+        )
+    })
+}
@@ -0,0 +1,443 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::ast::{
+    EnumDecl, Expression, FieldValue, FunctionDecl, Pattern, Slot, Span, Statement, StructDecl,
+};
+use crate::checker::CheckError;
+use crate::functions::FunctionContainer;
+
+// Binds every variable to the slot it will live in while running, so the executor indexes the
+// scopes directly instead of looking up names. Variables that are never declared are reported.
+pub fn resolve_program(program: &[Statement], source: &str) -> Vec<CheckError> {
+    let mut resolver = Resolver {
+        source,
+        scopes: vec![Rc::new(RefCell::new(HashMap::new()))],
+        structs: HashMap::new(),
+        enums: HashMap::new(),
+        builtins: FunctionContainer::new(),
+        function_bodies: Vec::new(),
+        errors: Vec::new(),
+    };
+    resolver.collect_types(program);
+    resolver.resolve_statements(program);
+
+    // Function bodies run after the whole script is declared, so they see every global.
+    // Function literals also see the variables declared after them in the scopes they capture.
+    while let Some((decl, scopes)) = resolver.function_bodies.pop() {
+        resolver.resolve_function_body(&decl, scopes);
+    }
+    resolver.errors
+}
+
+// A name maps to its index in the scope, scopes are shared with the function literals in them.
+type ResolverScope = Rc<RefCell<HashMap<String, usize>>>;
+
+// The scopes match the ones of the VariableContainer.
+struct Resolver<'a> {
+    source: &'a str,
+    scopes: Vec<ResolverScope>,
+    structs: HashMap<String, Rc<StructDecl>>,
+    enums: HashMap<String, Rc<EnumDecl>>,
+    builtins: FunctionContainer, // For the names of the builtin modules.
+    function_bodies: Vec<(Rc<FunctionDecl>, Vec<ResolverScope>)>, // With the scopes they see.
+    errors: Vec<CheckError>,
+}
+
+impl Resolver<'_> {
+    fn error(&mut self, span: Span, message: String) {
+        self.errors
+            .push(CheckError::new(span, self.source, message));
+    }
+
+    // Structs and enums are declared at the top level and can be used before the line
+    // declaring them.
+    fn collect_types(&mut self, program: &[Statement]) {
+        for statement in program {
+            match statement {
+                Statement::StructDecl(decl) => {
+                    if self.structs.contains_key(&decl.name) {
+                        let message = format!("Struct '{}' is already declared.", decl.name);
+                        self.error(decl.span, message);
+                        continue;
+                    }
+                    let fields = decl.fields.iter().map(|field| &field.name);
+                    self.check_unique(fields, decl.span, "Struct", &decl.name, "field");
+                    self.structs.insert(decl.name.clone(), decl.clone());
+                }
+                Statement::EnumDecl(decl) => {
+                    if self.enums.contains_key(&decl.name) {
+                        let message = format!("Enum '{}' is already declared.", decl.name);
+                        self.error(decl.span, message);
+                        continue;
+                    }
+                    let variants = decl.variants.iter().map(|variant| &variant.name);
+                    self.check_unique(variants, decl.span, "Enum", &decl.name, "variant");
+                    self.enums.insert(decl.name.clone(), decl.clone());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn check_unique<'n>(
+        &mut self,
+        names: impl Iterator<Item = &'n String>,
+        span: Span,
+        kind: &str,
+        type_name: &str,
+        member: &str,
+    ) {
+        let mut seen = Vec::new();
+        for name in names {
+            if seen.contains(&name) {
+                let message = format!(
+                    "{} '{}' has more than one {} named '{}'.",
+                    kind, type_name, member, name
+                );
+                self.error(span, message);
+            }
+            seen.push(name);
+        }
+    }
+
+    // Finds the enum of a variant and checks it gets as many values as the variant holds.
+    fn resolve_variant(
+        &mut self,
+        enum_name: &str,
+        variant: &str,
+        count: usize,
+        span: Span,
+    ) -> Option<Rc<EnumDecl>> {
+        let decl = match self.enums.get(enum_name) {
+            Some(decl) => decl.clone(),
+            None => {
+                self.error(span, format!("Unknown enum '{}'.", enum_name));
+                return None;
+            }
+        };
+        match decl.variants.iter().find(|v| v.name == variant) {
+            Some(found) if found.fields.len() != count => {
+                let message = format!(
+                    "Variant '{}::{}' holds {} values but got {}.",
+                    enum_name,
+                    variant,
+                    found.fields.len(),
+                    count
+                );
+                self.error(span, message);
+            }
+            Some(_) => {}
+            None => {
+                let message = format!("Enum '{}' has no variant '{}'.", enum_name, variant);
+                self.error(span, message);
+            }
+        }
+        Some(decl)
+    }
+
+    fn resolve_pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Variant {
+                enum_name,
+                variant,
+                items,
+                decl,
+                span,
+            } => {
+                decl.replace(self.resolve_variant(enum_name, variant, items.len(), *span));
+                for item in items {
+                    self.resolve_pattern(item);
+                }
+            }
+            Pattern::List { items, .. } => {
+                for item in items {
+                    self.resolve_pattern(item);
+                }
+            }
+            Pattern::Wildcard | Pattern::Literal(_) | Pattern::Binding(_) => {}
+        }
+    }
+
+    fn resolve_statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.resolve_statement(statement);
+        }
+    }
+
+    // Searches from the current scope outwards, the depth is counted from the current one.
+    fn find_slot(&self, name: &str) -> Option<Slot> {
+        let depth = self.scopes.len();
+        self.scopes.iter().enumerate().rev().find_map(|(i, scope)| {
+            scope.borrow().get(name).map(|index| Slot {
+                depth: depth - 1 - i,
+                index: *index,
+            })
+        })
+    }
+
+    fn resolve_variable(&mut self, name: &str, span: Span) -> Option<Slot> {
+        let slot = self.find_slot(name);
+        if slot.is_none() {
+            self.error(span, format!("Unknown variable '{}'.", name));
+        }
+        slot
+    }
+
+    fn resolve_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::VarDecl {
+                name, value, index, ..
+            } => {
+                // The value is resolved first, it can't see the variable it declares.
+                if let Some(value) = value {
+                    self.resolve_expression(value);
+                }
+
+                // Redeclaring a variable in the same scope reuses its index.
+                let mut scope = self.scopes.last().unwrap().borrow_mut();
+                let next_index = scope.len();
+                let declared = *scope.entry(name.clone()).or_insert(next_index);
+                index.set(Some(declared));
+            }
+            Statement::Assign {
+                name,
+                value,
+                slot,
+                span,
+            } => {
+                self.resolve_expression(value);
+                slot.set(self.resolve_variable(name, *span));
+            }
+            Statement::FieldAssign {
+                name,
+                value,
+                slot,
+                span,
+                ..
+            } => {
+                self.resolve_expression(value);
+                slot.set(self.resolve_variable(name, *span));
+            }
+            Statement::StructDecl(decl) => {
+                if self.scopes.len() > 1 {
+                    let message = "Structs can only be declared at the top level.".to_string();
+                    self.error(decl.span, message);
+                }
+            }
+            Statement::EnumDecl(decl) => {
+                if self.scopes.len() > 1 {
+                    let message = "Enums can only be declared at the top level.".to_string();
+                    self.error(decl.span, message);
+                }
+            }
+            // Every arm gets a scope that starts with the bindings of its pattern.
+            Statement::Match { value, arms, .. } => {
+                self.resolve_expression(value);
+                for arm in arms {
+                    self.resolve_pattern(&arm.pattern);
+                    let scope: ResolverScope = Rc::new(RefCell::new(HashMap::new()));
+                    for name in arm.pattern.bindings() {
+                        if scope.borrow().contains_key(&name) {
+                            let message =
+                                format!("'{}' is bound more than once in the pattern.", name);
+                            self.error(arm.span, message);
+                            continue;
+                        }
+                        let index = scope.borrow().len();
+                        scope.borrow_mut().insert(name, index);
+                    }
+                    self.scopes.push(scope);
+                    self.resolve_statements(&arm.body);
+                    self.scopes.pop();
+                }
+            }
+            Statement::FunctionDecl(decl) => {
+                let globals = vec![self.scopes[0].clone()];
+                self.function_bodies.push((decl.clone(), globals));
+            }
+            Statement::Return { value, .. } => {
+                if let Some(value) = value {
+                    self.resolve_expression(value);
+                }
+            }
+            Statement::If {
+                condition, body, ..
+            } => {
+                self.resolve_expression(condition);
+                self.scopes.push(Rc::new(RefCell::new(HashMap::new())));
+                self.resolve_statements(body);
+                self.scopes.pop();
+            }
+            Statement::Throw { value, .. } => self.resolve_expression(value),
+            // The catch block gets a scope that starts with the error.
+            Statement::Try {
+                body,
+                catch,
+                finally,
+                ..
+            } => {
+                self.scopes.push(Rc::new(RefCell::new(HashMap::new())));
+                self.resolve_statements(body);
+                self.scopes.pop();
+                if let Some(catch) = catch {
+                    let scope = HashMap::from([(catch.name.clone(), 0)]);
+                    self.scopes.push(Rc::new(RefCell::new(scope)));
+                    self.resolve_statements(&catch.body);
+                    self.scopes.pop();
+                }
+                if let Some(finally) = finally {
+                    self.scopes.push(Rc::new(RefCell::new(HashMap::new())));
+                    self.resolve_statements(finally);
+                    self.scopes.pop();
+                }
+            }
+            Statement::Expression(expression) => self.resolve_expression(expression),
+            // The module is a constant in the scope.
+            Statement::Import {
+                name, index, span, ..
+            } => {
+                if self.scopes.len() > 1 {
+                    let message = "Imports can only be at the top level.".to_string();
+                    self.error(*span, message);
+                }
+                let mut scope = self.scopes.last().unwrap().borrow_mut();
+                let next_index = scope.len();
+                let declared = *scope.entry(name.clone()).or_insert(next_index);
+                index.set(Some(declared));
+            }
+            Statement::Use { module, span } => {
+                if self.scopes.len() > 1 {
+                    let message = "Use statements can only be at the top level.".to_string();
+                    self.error(*span, message);
+                }
+                if !self.builtins.has_module(module) {
+                    self.error(*span, format!("Unknown module '{}'.", module));
+                }
+            }
+            Statement::DebugVar { .. } => {}
+        }
+    }
+
+    // Functions see the scopes they captured and their parameters, which are indexed in order
+    // with the rest parameter last. Default values only see the parameters before them.
+    fn resolve_function_body(&mut self, decl: &FunctionDecl, scopes: Vec<ResolverScope>) {
+        let params: ResolverScope = Rc::new(RefCell::new(HashMap::new()));
+        self.scopes = scopes;
+        self.scopes.push(params.clone());
+        for (i, param) in decl.params.iter().enumerate() {
+            if let Some(default) = &param.default {
+                self.resolve_expression(default);
+            }
+            params.borrow_mut().insert(param.name.clone(), i);
+        }
+        if let Some(rest) = &decl.rest {
+            params.borrow_mut().insert(rest.clone(), decl.params.len());
+        }
+        self.resolve_statements(&decl.body);
+        self.scopes.truncate(1);
+    }
+
+    fn resolve_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Literal { .. } => {}
+            Expression::Variable { name, slot, span } => {
+                slot.set(self.resolve_variable(name, *span))
+            }
+            // Calling a variable holding a function, otherwise the name is a function.
+            // A namespace is the variable holding an imported module or a builtin module.
+            Expression::Call {
+                namespace,
+                name,
+                args,
+                keywords,
+                slot,
+                span,
+            } => {
+                match namespace {
+                    Some(namespace) => {
+                        slot.set(self.find_slot(namespace));
+                        if slot.get().is_none() && !self.builtins.has_module(namespace) {
+                            self.error(*span, format!("Unknown module '{}'.", namespace));
+                        }
+                    }
+                    None => slot.set(self.find_slot(name)),
+                }
+                for arg in args {
+                    self.resolve_expression(arg);
+                }
+                for keyword in keywords {
+                    self.resolve_expression(&keyword.value);
+                }
+            }
+            Expression::List { items, .. } => {
+                for item in items {
+                    self.resolve_expression(item);
+                }
+            }
+            Expression::Struct {
+                name,
+                fields,
+                decl,
+                span,
+            } => {
+                for field in fields {
+                    self.resolve_expression(&field.value);
+                }
+                match self.structs.get(name).cloned() {
+                    Some(found) => {
+                        self.check_fields(&found, fields, *span);
+                        decl.replace(Some(found));
+                    }
+                    None => self.error(*span, format!("Unknown struct '{}'.", name)),
+                }
+            }
+            Expression::Field { value, .. } => self.resolve_expression(value),
+            Expression::Variant {
+                enum_name,
+                variant,
+                args,
+                decl,
+                span,
+            } => {
+                for arg in args {
+                    self.resolve_expression(arg);
+                }
+                decl.replace(self.resolve_variant(enum_name, variant, args.len(), *span));
+            }
+            Expression::Function(decl) => {
+                self.function_bodies
+                    .push((decl.clone(), self.scopes.clone()));
+            }
+        }
+    }
+
+    // A struct literal gives every field of the struct exactly once.
+    fn check_fields(&mut self, decl: &StructDecl, fields: &[FieldValue], span: Span) {
+        for (i, field) in fields.iter().enumerate() {
+            if !decl.fields.iter().any(|f| f.name == field.name) {
+                let message = format!("Struct '{}' has no field '{}'.", decl.name, field.name);
+                self.error(field.span, message);
+            } else if fields[..i].iter().any(|f| f.name == field.name) {
+                let message = format!("Field '{}' is given more than once.", field.name);
+                self.error(field.span, message);
+            }
+        }
+
+        let missing: Vec<&str> = decl
+            .fields
+            .iter()
+            .filter(|field| !fields.iter().any(|f| f.name == field.name))
+            .map(|field| field.name.as_str())
+            .collect();
+        if !missing.is_empty() {
+            let message = format!(
+                "Struct '{}' is missing the fields {}.",
+                decl.name,
+                missing.join(", ")
+            );
+            self.error(span, message);
+        }
+    }
+}
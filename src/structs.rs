@@ -0,0 +1,106 @@
+use std::rc::Rc;
+
+use crate::ast::{StructDecl, StructField};
+use crate::errors::{raise, ErrorKind};
+use crate::variables::{Object, VariableContent};
+
+// Builds a struct from its fields in the order they were written, the resolver already
+// reported fields that are missing or unknown.
+pub fn build_struct(
+    decl: &Rc<StructDecl>,
+    mut values: Vec<(String, VariableContent)>,
+) -> VariableContent {
+    let fields = decl
+        .fields
+        .iter()
+        .map(|field| {
+            let index = values
+                .iter()
+                .position(|(name, _)| *name == field.name)
+                .unwrap_or_else(|| {
+                    let message =
+                        format!("Struct {} is missing the field {}.", decl.name, field.name);
+                    raise(ErrorKind::Field, message)
+                });
+            let (_, value) = values.swap_remove(index);
+            check_field(decl, field, &value);
+            value
+        })
+        .collect();
+    VariableContent::record(decl.clone(), fields)
+}
+
+// Panics if the value doesn't match the annotation of the field, if it has one.
+fn check_field(decl: &StructDecl, field: &StructField, value: &VariableContent) {
+    if let Some(expected) = field.annotation {
+        if expected != value.data_type {
+            let message = format!(
+                "Field {} of struct {} should be of type {:?}, got {:?}.",
+                field.name, decl.name, expected, value.data_type
+            );
+            raise(ErrorKind::Type, message);
+        }
+    }
+}
+
+fn field_index(decl: &StructDecl, field: &str) -> usize {
+    decl.fields
+        .iter()
+        .position(|f| f.name == field)
+        .unwrap_or_else(|| {
+            let message = format!("Struct {} has no field {}.", decl.name, field);
+            raise(ErrorKind::Field, message)
+        })
+}
+
+// Modules give their functions and constants like fields.
+pub fn get_field(value: &VariableContent, field: &str) -> VariableContent {
+    if let Some(Object::Module { path, exports }) = value.object.as_deref() {
+        return match exports.iter().find(|(name, _)| name == field) {
+            Some((_, export)) => export.clone(),
+            None => raise(
+                ErrorKind::Field,
+                format!("Module {} has no function or constant {}.", path, field),
+            ),
+        };
+    }
+    match value.as_struct() {
+        Some((decl, fields)) => fields[field_index(decl, field)].clone(),
+        None => raise(
+            ErrorKind::Type,
+            format!(
+                "Can't get field {} of a value of type {:?}.",
+                field, value.data_type
+            ),
+        ),
+    }
+}
+
+// Structs are values, so setting a field gives a changed copy. The path leads through the
+// nested structs to the field that is set.
+pub fn set_field(
+    record: &VariableContent,
+    path: &[String],
+    value: VariableContent,
+) -> VariableContent {
+    let (decl, fields) = match record.as_struct() {
+        Some(record) => record,
+        None => raise(
+            ErrorKind::Type,
+            format!(
+                "Can't set field {} of a value of type {:?}.",
+                path[0], record.data_type
+            ),
+        ),
+    };
+    let index = field_index(decl, &path[0]);
+    let value = match path.len() {
+        1 => value,
+        _ => set_field(&fields[index], &path[1..], value),
+    };
+    check_field(decl, &decl.fields[index], &value);
+
+    let mut fields = fields.clone();
+    fields[index] = value;
+    VariableContent::record(decl.clone(), fields)
+}
@@ -11,5 +11,5 @@ pub fn make_string(src_string: &str) -> String {
     output_string = output_string.replace("\\r", "\r");
     output_string = output_string.replace("\x5c\x22", "\"");
 
-    return output_string;
+    output_string
 }
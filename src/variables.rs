@@ -1,11 +1,15 @@
-use std::collections::HashMap;
+use std::cell::{Ref, RefCell};
+use std::fmt;
+use std::rc::Rc;
 
 use crate::{
-    functions::{match_rule_func_call_decl, FunctionContainer},
-    type_string::make_string,
-    Rule,
+    ast::{EnumDecl, Expression, FunctionDecl, Slot, StructDecl},
+    bytecode::{CompiledFunction, VariableCell},
+    errors::{raise, ErrorKind},
+    execute_code::CodeExecutor,
+    host::Host,
+    limits::{check_memory, size},
 };
-use pest::iterators::Pair;
 
 // All the different types a variable could be.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -15,33 +19,310 @@ pub enum VariableTypes {
     FLOAT,
     BOOL,
     NULL,
+    FUNCTION,
+    LIST,
+    STRUCT,
+    ENUM,
+    MODULE,
 }
 
+impl VariableTypes {
+    // Gets the type from an annotation like `var x: int`.
+    pub fn from_annotation(annotation: &str) -> VariableTypes {
+        match annotation {
+            "int" => VariableTypes::INT,
+            "string" => VariableTypes::STRING,
+            "float" => VariableTypes::FLOAT,
+            "bool" => VariableTypes::BOOL,
+            "null" => VariableTypes::NULL,
+            "function" => VariableTypes::FUNCTION,
+            "list" => VariableTypes::LIST,
+            "struct" => VariableTypes::STRUCT,
+            "enum" => VariableTypes::ENUM,
+            _ => panic!("Unknown type annotation '{}'.", annotation),
+        }
+    }
+}
+
+// A scope is shared with the functions that captured it.
+pub type Scope = Rc<RefCell<Vec<Variable>>>;
+
 // Contains the actual variables and has some methods.
 pub struct VariableContainer {
-    variables: Vec<HashMap<String, VariableContent>>,
+    variables: Vec<Scope>,
+    pub max_depth: Option<usize>, // The most scopes there can be at once.
 }
 
 // Stores information about the variable that is stored, like the value and type.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct VariableContent {
     pub value: String,
     pub data_type: VariableTypes,
+    pub object: Option<Rc<Object>>, // The data of values that can't be kept in the string.
+    pub nested: usize,              // The bytes of the values in the object, see limits::size.
+}
+
+impl VariableContent {
+    pub fn new(value: String, data_type: VariableTypes) -> VariableContent {
+        VariableContent {
+            value,
+            data_type,
+            object: None,
+            nested: 0,
+        }
+    }
+
+    // The value of empty variables and functions that don't return anything.
+    pub fn null() -> VariableContent {
+        VariableContent::new("".to_string(), VariableTypes::NULL)
+    }
+
+    pub fn function(function: FunctionValue, params: &[String]) -> VariableContent {
+        VariableContent {
+            value: format!("fn({})", params.join(", ")),
+            data_type: VariableTypes::FUNCTION,
+            object: Some(Rc::new(Object::Function(function))),
+            nested: 0,
+        }
+    }
+
+    // The value of a list is how print shows it.
+    pub fn list(items: Vec<VariableContent>) -> VariableContent {
+        let nested = check_items(&items);
+        let shown: Vec<String> = items.iter().map(VariableContent::shown_inside).collect();
+        VariableContent {
+            value: format!("[{}]", shown.join(", ")),
+            data_type: VariableTypes::LIST,
+            object: Some(Rc::new(Object::List(items))),
+            nested,
+        }
+    }
+
+    // The fields are in the order the struct declares them, print shows them like `P { x: 1 }`.
+    pub fn record(decl: Rc<StructDecl>, fields: Vec<VariableContent>) -> VariableContent {
+        let nested = check_items(&fields);
+        let shown: Vec<String> = decl
+            .fields
+            .iter()
+            .zip(&fields)
+            .map(|(field, value)| format!("{}: {}", field.name, value.shown_inside()))
+            .collect();
+        VariableContent {
+            value: format_struct(&decl.name, &shown),
+            data_type: VariableTypes::STRUCT,
+            object: Some(Rc::new(Object::Struct { decl, fields })),
+            nested,
+        }
+    }
+
+    // Print shows variants like `Shape::Circle(2)`, or `Shape::Empty` without values.
+    pub fn variant(
+        decl: Rc<EnumDecl>,
+        variant: usize,
+        payload: Vec<VariableContent>,
+    ) -> VariableContent {
+        let nested = check_items(&payload);
+        let shown: Vec<String> = payload.iter().map(VariableContent::shown_inside).collect();
+        VariableContent {
+            value: format_variant(&decl.name, &decl.variants[variant].name, &shown),
+            data_type: VariableTypes::ENUM,
+            object: Some(Rc::new(Object::Enum {
+                decl,
+                variant,
+                payload,
+            })),
+            nested,
+        }
+    }
+
+    // The functions and constants an imported file gives, print shows the path of the file.
+    pub fn module(path: &str, exports: Vec<(String, VariableContent)>) -> VariableContent {
+        VariableContent {
+            value: format!("module {}", path),
+            data_type: VariableTypes::MODULE,
+            object: Some(Rc::new(Object::Module {
+                path: path.to_string(),
+                exports,
+            })),
+            nested: 0,
+        }
+    }
+
+    // How the value is shown inside of a list or struct.
+    pub fn shown_inside(&self) -> String {
+        match self.data_type {
+            VariableTypes::STRING => format!("\"{}\"", self.value),
+            VariableTypes::NULL => "null".to_string(),
+            _ => self.value.clone(),
+        }
+    }
+
+    pub fn as_function(&self) -> Option<&FunctionValue> {
+        match self.object.as_deref() {
+            Some(Object::Function(function)) => Some(function),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&Vec<VariableContent>> {
+        match self.object.as_deref() {
+            Some(Object::List(items)) => Some(items),
+            _ => None,
+        }
+    }
+
+    // The enum, the index of the variant and the values it holds.
+    pub fn as_variant(&self) -> Option<(&Rc<EnumDecl>, usize, &Vec<VariableContent>)> {
+        match self.object.as_deref() {
+            Some(Object::Enum {
+                decl,
+                variant,
+                payload,
+            }) => Some((decl, *variant, payload)),
+            _ => None,
+        }
+    }
+
+    pub fn as_struct(&self) -> Option<(&Rc<StructDecl>, &Vec<VariableContent>)> {
+        match self.object.as_deref() {
+            Some(Object::Struct { decl, fields }) => Some((decl, fields)),
+            _ => None,
+        }
+    }
+}
+
+// The object is left out, a function can capture the scope it is in.
+impl fmt::Debug for VariableContent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("VariableContent")
+            .field("value", &self.value)
+            .field("data_type", &self.data_type)
+            .finish()
+    }
+}
+
+// Raises before a collection of the values takes more memory than the script has left. Its
+// string shows every value with a few bytes for the quotes and separators around them, and
+// it holds the values themselves. Gives back the bytes of those.
+fn check_items(items: &[VariableContent]) -> usize {
+    let nested = items
+        .iter()
+        .fold(0, |total: usize, item| total.saturating_add(size(item)));
+    let shown = items.iter().fold(0, |total: usize, item| {
+        total.saturating_add(item.value.len() + 4)
+    });
+    check_memory(shown.saturating_add(nested));
+    nested
+}
+
+// Like `Point { x: 1, y: 2 }`, or `Empty {}` without fields.
+pub fn format_struct(name: &str, fields: &[String]) -> String {
+    match fields.is_empty() {
+        true => format!("{} {{}}", name),
+        false => format!("{} {{ {} }}", name, fields.join(", ")),
+    }
+}
+
+pub fn format_variant(enum_name: &str, variant: &str, payload: &[String]) -> String {
+    match payload.is_empty() {
+        true => format!("{}::{}", enum_name, variant),
+        false => format!("{}::{}({})", enum_name, variant, payload.join(", ")),
+    }
+}
+
+pub enum Object {
+    Function(FunctionValue),
+    List(Vec<VariableContent>),
+    Struct {
+        decl: Rc<StructDecl>,
+        fields: Vec<VariableContent>,
+    },
+    Enum {
+        decl: Rc<EnumDecl>,
+        variant: usize,
+        payload: Vec<VariableContent>,
+    },
+    Module {
+        path: String,
+        exports: Vec<(String, VariableContent)>,
+    },
 }
 
+// A function stored in a variable, the executor and the VM each capture variables their own way.
+pub enum FunctionValue {
+    Closure {
+        decl: Rc<FunctionDecl>,
+        scopes: Vec<Scope>,
+        module: usize, // The file it is in, its calls by name find the functions of that file.
+    },
+    Compiled {
+        function: Rc<CompiledFunction>,
+        upvalues: Vec<VariableCell>,
+    },
+}
+
+// A variable as it lives in a scope, with the rules for assigning to it.
+#[derive(Debug, Clone)]
+pub struct Variable {
+    pub name: String,
+    pub content: VariableContent,
+    pub constant: bool,
+    pub annotation: Option<VariableTypes>,
+}
+
+impl Variable {
+    // Panics if the value doesn't match the annotated type.
+    pub fn check_type(&self, value: &VariableContent) {
+        check_annotation(&self.name, self.annotation, value.data_type);
+    }
+
+    // How debug.var shows the variable.
+    pub fn debug_string(&self) -> String {
+        let mut name = self.name.clone();
+        if let Some(annotation) = self.annotation {
+            name.push_str(format!(": {:?}", annotation).as_str());
+        }
+        if self.constant {
+            name.push_str(" (const)");
+        }
+        format!("{}: {:?}", name, self.content)
+    }
+}
+
+// Panics if the value doesn't match the annotation of the variable, if it has one.
+pub fn check_annotation(name: &str, annotation: Option<VariableTypes>, data_type: VariableTypes) {
+    if let Some(expected) = annotation {
+        if expected != data_type {
+            let message = format!(
+                "Variable {} is of type {:?}, can't assign a value of type {:?}.",
+                name, expected, data_type
+            );
+            raise(ErrorKind::Type, message);
+        }
+    }
+}
+
+// The variables are found through the slots the resolver gave them, so no names are looked up.
 impl VariableContainer {
-    // Creates a new and empty hashmap for the variables to live in.
+    // Creates a new and empty scope for the variables to live in.
     pub fn new() -> VariableContainer {
         let mut ret = VariableContainer {
             variables: Vec::new(),
+            max_depth: None,
         };
-        ret.variables.push(HashMap::new());
+        ret.scope_in();
         ret
     }
 
     // Moves into the next scope.
     pub fn scope_in(&mut self) {
-        self.variables.push(HashMap::new());
+        if let Some(max) = self.max_depth {
+            if self.variables.len() >= max {
+                let message = format!("The script went more than {} scopes deep.", max);
+                raise(ErrorKind::ScopeDepth, message);
+            }
+        }
+        self.variables.push(Rc::new(RefCell::new(Vec::new())));
     }
 
     // Moves out of the scope.
@@ -49,203 +330,138 @@ impl VariableContainer {
         self.variables.pop();
     }
 
-    // Adds a variable to the hash.
-    pub fn add_variable(&mut self, name: &str, value: VariableContent) {
-        self.variables
-            .last_mut()
-            .unwrap()
-            .insert(name.to_string(), value);
+    // The scopes a function literal captures, which are all of the current ones.
+    pub fn capture(&self) -> Vec<Scope> {
+        self.variables.clone()
     }
 
-    // Sets a variable in the first scope it exists in.
-    pub fn set_variable(&mut self, name: &str, var_value: VariableContent) {
-        let var_iter = self.variables.iter_mut().rev();
+    // The scopes a named function sees, which is only the global one.
+    pub fn globals(&self) -> Vec<Scope> {
+        vec![self.variables[0].clone()]
+    }
 
-        for scope in var_iter {
-            if scope.contains_key(name) {
-                scope.insert(name.to_string(), var_value.clone());
-                return;
-            }
+    // Switches to the scopes of a function with a new scope for its parameters.
+    // Returns the scopes of the caller.
+    pub fn function_in(&mut self, scopes: Vec<Scope>) -> Vec<Scope> {
+        let caller_scopes = std::mem::replace(&mut self.variables, scopes);
+        self.scope_in();
+        caller_scopes
+    }
+
+    // Restores the scopes of the caller.
+    pub fn function_out(&mut self, caller_scopes: Vec<Scope>) {
+        self.variables = caller_scopes;
+    }
+
+    // Adds a variable at its index in the current scope, redeclaring one replaces it.
+    pub fn add_variable(&mut self, index: usize, variable: Variable) {
+        let mut scope = self.variables.last().unwrap().borrow_mut();
+        if scope.get(index).is_some_and(|var| var.constant) {
+            let message = format!(
+                "Couldn't declare {} as it is already a constant.",
+                variable.name
+            );
+            raise(ErrorKind::Constant, message);
         }
 
-        panic!("Couldn't set variable {} as it doesn't exist.", name);
+        variable.check_type(&variable.content);
+        if index < scope.len() {
+            scope[index] = variable;
+        } else {
+            scope.push(variable);
+        }
     }
 
-    // Gets the VariableContent of a variable.
-    pub fn get_variable(&self, name: &str) -> &VariableContent {
-        let var_iter = self.variables.iter().rev();
+    // A global can be used by a function before the line declaring it ran.
+    fn find_scope(&self, slot: Slot) -> &Scope {
+        &self.variables[self.variables.len() - 1 - slot.depth]
+    }
 
-        for scope in var_iter {
-            if scope.contains_key(name) {
-                return scope.get(name).unwrap();
+    // Sets a variable in the scope the resolver found it in.
+    pub fn set_variable(&mut self, slot: Slot, name: &str, var_value: VariableContent) {
+        match self.find_scope(slot).borrow_mut().get_mut(slot.index) {
+            Some(variable) => {
+                if variable.constant {
+                    raise(
+                        ErrorKind::Constant,
+                        format!("Couldn't set {} as it is a constant.", name),
+                    );
+                }
+                variable.check_type(&var_value);
+                variable.content = var_value;
             }
+            None => raise(
+                ErrorKind::UnknownVariable,
+                format!("Couldn't set variable {} as it doesn't exist.", name),
+            ),
         }
-
-        panic!("Couldn't get variable {}.", name);
     }
 
-    // Prints out the content of the hashmap for debug purposes.
-    #[allow(dead_code)]
-    pub fn debug_print_vars(&self) {
-        for (k, v) in self.variables.last().unwrap().iter() {
-            println!("{}: {:?}", k, v);
+    // Borrows the VariableContent of a variable, the scope can't be changed while it is held.
+    pub fn get_variable(&self, slot: Slot, name: &str) -> Ref<'_, VariableContent> {
+        let scope = self.find_scope(slot).borrow();
+        match Ref::filter_map(scope, |scope| Some(&scope.get(slot.index)?.content)) {
+            Ok(content) => content,
+            Err(_) => raise(
+                ErrorKind::UnknownVariable,
+                format!("Couldn't get variable {}.", name),
+            ),
         }
     }
-}
 
-pub fn match_rule_vardecl(
-    pair: Pair<Rule>,
-    var_container: &mut VariableContainer,
-    function_container: &FunctionContainer,
-) {
-    // Moves into the useful info.
-    let inner = pair.into_inner();
-
-    // Pre-allocates memory for the add_variable call later.
-    let mut var_name = "";
-    let mut var_content: VariableContent = VariableContent {
-        value: "".to_string(),
-        data_type: VariableTypes::NULL,
-    };
+    // The bytes held by the variables of the scopes, see limits::size.
+    pub fn size(&self) -> usize {
+        self.variables
+            .iter()
+            .map(|scope| {
+                let scope = scope.borrow();
+                scope.iter().map(|var| size(&var.content)).sum::<usize>()
+            })
+            .sum()
+    }
 
-    // Gets the name and content info.
-    for info in inner {
-        match info.as_rule() {
-            Rule::var_name => {
-                var_name = info.as_str();
-            }
+    // Prints out the content of the current scope for debug purposes.
+    pub fn debug_print_vars(&self, host: &mut Host) {
+        let scope = self.variables.last().unwrap().borrow();
+        let mut vars: Vec<_> = scope.iter().collect();
+        vars.sort_by(|a, b| a.name.cmp(&b.name));
 
-            Rule::var_types => {
-                // Don't know why, but it works.
-                let type_info = info
-                    .into_inner()
-                    .into_iter()
-                    .next()
-                    .expect("Error parsing type.");
-
-                // Assigns the types and value for the variables.
-                match type_info.as_rule() {
-                    Rule::type_int => {
-                        var_content.data_type = VariableTypes::INT;
-                        var_content.value = type_info.as_str().to_string();
-                    }
-                    Rule::type_bool => {
-                        var_content.data_type = VariableTypes::BOOL;
-                        var_content.value = type_info.as_str().to_string();
-                    }
-                    Rule::type_float => {
-                        var_content.data_type = VariableTypes::FLOAT;
-                        var_content.value = type_info.as_str().to_string();
-                    }
-                    Rule::type_string => {
-                        var_content.data_type = VariableTypes::STRING;
-                        var_content.value = make_string(type_info.as_str());
-                    }
-                    Rule::var_name => {
-                        let result = var_container.get_variable(type_info.as_str());
-                        var_content.data_type = result.data_type;
-                        var_content.value = result.value.clone();
-                    }
-                    Rule::func_call_decl => {
-                        let result =
-                            match_rule_func_call_decl(type_info, function_container, var_container);
-                        var_content.data_type = result.data_type;
-                        var_content.value = result.value.clone();
-                    }
-                    _ => panic!("Type not implemented: {:?}", type_info.as_rule()),
-                }
-            }
-            _ => {
-                panic!("Not implemented: {}", info);
-            }
+        for v in vars {
+            host.write(&format!("{}\n", v.debug_string()));
         }
     }
+}
 
-    // Finally adds the variable.
-    var_container.add_variable(var_name, var_content);
+impl Default for VariableContainer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-// Only sets a variable but doesn't init one.
-pub fn match_rule_reassign_variable(
-    pair: Pair<Rule>,
-    var_container: &mut VariableContainer,
-    function_container: &FunctionContainer,
+// Declares a variable or constant, without a value it starts out as NULL.
+pub fn execute_var_decl(
+    name: &str,
+    annotation: Option<VariableTypes>,
+    constant: bool,
+    value: Option<&Expression>,
+    index: usize,
+    executor: &mut CodeExecutor,
 ) {
-    // Moves into the useful info.
-    let inner = pair.into_inner();
-
-    // Pre-allocates memory for the add_variable call later.
-    let mut var_name = "";
-    let mut var_content: VariableContent = VariableContent {
-        value: "".to_string(),
-        data_type: VariableTypes::NULL,
+    let var_content = match value {
+        Some(value) => executor.evaluate(value),
+        None => VariableContent::null(),
     };
-
-    // Gets the name and content info.
-    for info in inner {
-        match info.as_rule() {
-            Rule::var_name => {
-                var_name = info.as_str();
-            }
-
-            Rule::var_types => {
-                // Don't know why, but it works.
-                let type_info = info
-                    .into_inner()
-                    .into_iter()
-                    .next()
-                    .expect("Error parsing type.");
-
-                // Assigns the types and value for the variables.
-                match type_info.as_rule() {
-                    Rule::type_int => {
-                        var_content.data_type = VariableTypes::INT;
-                        var_content.value = type_info.as_str().to_string();
-                    }
-                    Rule::type_float => {
-                        var_content.data_type = VariableTypes::FLOAT;
-                        var_content.value = type_info.as_str().to_string();
-                    }
-                    Rule::type_bool => {
-                        var_content.data_type = VariableTypes::BOOL;
-                        var_content.value = type_info.as_str().to_string();
-                    }
-                    Rule::type_string => {
-                        var_content.data_type = VariableTypes::STRING;
-                        var_content.value = make_string(type_info.as_str());
-                    }
-                    Rule::var_name => {
-                        let result = var_container.get_variable(type_info.as_str());
-                        var_content.data_type = result.data_type;
-                        var_content.value = result.value.clone();
-                    }
-                    Rule::func_call_decl => {
-                        let result =
-                            match_rule_func_call_decl(type_info, function_container, var_container);
-                        var_content.data_type = result.data_type;
-                        var_content.value = result.value.clone();
-                    }
-                    _ => panic!("Type not implemented: {:?}", type_info.as_rule()),
-                }
-            }
-            _ => {
-                panic!("Not implemented: {}", info);
-            }
-        }
-    }
-
-    // Finally adds the variable.
-    var_container.set_variable(var_name, var_content);
+    let variable = Variable {
+        name: name.to_string(),
+        content: var_content,
+        constant,
+        annotation,
+    };
+    executor.var_container.add_variable(index, variable);
 }
 
-// Match case for an empty init.
-pub fn match_rule_empty_var(pair: Pair<Rule>, var_container: &mut VariableContainer) {
-    let var_name = pair.into_inner().into_iter().next().unwrap().as_str();
-    var_container.add_variable(
-        var_name,
-        VariableContent {
-            data_type: VariableTypes::NULL,
-            value: "".to_string(),
-        },
-    );
+// Only sets a variable but doesn't init one.
+pub fn execute_assign(name: &str, value: &Expression, slot: Slot, executor: &mut CodeExecutor) {
+    let var_content = executor.evaluate(value);
+    executor.var_container.set_variable(slot, name, var_content);
 }
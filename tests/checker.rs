@@ -0,0 +1,59 @@
+use std::process::Command;
+
+// What checking a script without running it reports.
+fn check(file: &str) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_simple_script"))
+        .arg("--check")
+        .arg(file)
+        .output()
+        .expect("Couldn't run simple_script.");
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn matches_on_bools_and_lists_can_cover_every_value() {
+    let warnings: Vec<String> = check("tests/scripts/matches.smpl")
+        .lines()
+        .filter(|line| line.contains("warning"))
+        .map(str::to_string)
+        .collect();
+    assert_eq!(
+        warnings,
+        [
+            "tests/scripts/matches.smpl:11:22: warning: The match doesn't cover false.",
+            "tests/scripts/matches.smpl:12:24: warning: The match doesn't cover [_].",
+        ]
+    );
+}
+
+#[test]
+fn type_errors_are_reported_where_they_are() {
+    let stderr = check("tests/scripts/check_types.smpl");
+    assert!(stderr.starts_with(
+        "tests/scripts/check_types.smpl:3:1: Variable 'count' is of type INT, can't assign a \
+         value of type STRING.\n    count = \"one\"\n    ^^^^^^^^^^^^^\n"
+    ));
+    let errors: Vec<&str> = stderr
+        .lines()
+        .filter(|line| line.starts_with("tests/"))
+        .map(|line| line.trim_start_matches("tests/scripts/check_types.smpl:"))
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            "3:1: Variable 'count' is of type INT, can't assign a value of type STRING.",
+            "4:4: The condition of an if should be of type BOOL, got INT.",
+            "5:11: Function 'add' can't take an argument of type STRING, expected one of \
+             [INT, FLOAT].",
+            "6:1: Unknown function 'missing'.",
+            "7:26: The function should return a value of type INT, got STRING.",
+            "8:30: The default value of parameter 'factor' should be of type FLOAT, got STRING.",
+            "9:1: Can't return outside of a function.",
+            "10:11: The INT 99999999999999999999 doesn't fit in 64 bits.",
+            "12:7: A value of type STRING has no fields.",
+            "13:1: Variable 'text' is of type STRING and can't be called.",
+            "14:7: Function 'str.len' doesn't take keyword arguments.",
+            "14:7: Function 'str.len' takes at least 1 arguments but got 0.",
+        ]
+    );
+}
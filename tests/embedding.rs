@@ -0,0 +1,152 @@
+use std::cell::RefCell;
+use std::fs;
+use std::io::{self, Cursor, Write};
+use std::path::PathBuf;
+use std::process;
+use std::rc::Rc;
+
+use simple_script::bytecode::compiler::compile_program;
+use simple_script::bytecode::vm::VirtualMachine;
+use simple_script::clock::FakeClock;
+use simple_script::execute_code::CodeExecutor;
+use simple_script::functions::FunctionContainer;
+use simple_script::host::Host;
+use simple_script::modules::load_modules;
+use simple_script::resolver::resolve_program;
+
+// A directory of its own for every test, the scripts write what they found into it.
+fn scratch(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("simple_script_{}_{}", name, process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+// An output the test can read after the scripts wrote to it, None makes every write fail.
+#[derive(Clone)]
+struct Captured(Rc<RefCell<Option<Vec<u8>>>>);
+
+impl Captured {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.borrow().clone().unwrap()).unwrap()
+    }
+}
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut *self.0.borrow_mut() {
+            Some(captured) => captured.extend_from_slice(buf),
+            None => return Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed")),
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Runs the script with the executor and the VM, each with the host the function makes, and
+// gives back the file output.txt the script wrote.
+fn run_both(name: &str, source: &str, make_host: impl Fn() -> Host) -> String {
+    let directory = scratch(name);
+    let path = directory.join("main.smpl");
+    fs::write(&path, source).unwrap();
+    let output = directory.join("output.txt");
+    let load = || {
+        let modules = load_modules(path.to_str().unwrap());
+        for module in &modules {
+            assert!(resolve_program(&module.program, &module.source).is_empty());
+        }
+        modules
+    };
+    let host = || Host {
+        root: directory.clone(),
+        ..make_host()
+    };
+
+    let mut executor = CodeExecutor::new();
+    executor.host = host();
+    executor
+        .run(load().into_iter().map(Rc::new).collect())
+        .unwrap();
+    let executed = fs::read_to_string(&output).unwrap();
+    fs::remove_file(&output).unwrap();
+
+    let compiled = compile_program(&load(), &FunctionContainer::new());
+    let mut vm = VirtualMachine::new(&compiled);
+    vm.set_host(host());
+    vm.run().unwrap();
+    let run = fs::read_to_string(&output).unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(executed, run);
+    executed
+}
+
+#[test]
+fn scripts_read_the_input_the_host_gives() {
+    let source = r#"
+var first = read_line()
+var name = input("name? ")
+var rest = read_all()
+write_file("output.txt", concat(first, "|", name, "|", rest, "|", read_line()))
+"#;
+    let output = run_both("input", source, || Host {
+        input: Box::new(Cursor::new("first\r\nAda\nthe\nrest\n")),
+        output: Box::new(io::sink()),
+        ..Host::new()
+    });
+    assert_eq!(output, "first|Ada|the\nrest\n|");
+}
+
+#[test]
+fn scripts_read_the_clock_the_host_gives() {
+    let source = r#"
+var start = time.now()
+time.sleep(1500)
+var times = concat(time.to_iso(start), " ", time.to_iso(time.now()))
+write_file("output.txt", concat(times, " ", time.monotonic()))
+"#;
+    // 2024-02-29T23:59:59Z, sleeping goes over to March.
+    let output = run_both("clock", source, || Host {
+        clock: Box::new(FakeClock::new(1_709_251_199_000)),
+        ..Host::new()
+    });
+    assert_eq!(
+        output,
+        "2024-02-29T23:59:59.000Z 2024-03-01T00:00:00.500Z 1500"
+    );
+}
+
+#[test]
+fn scripts_write_to_the_output_the_host_gives() {
+    let source = r#"
+var name = input("name? ")
+printf("{}!", name)
+print(" hi")
+debug.var
+write_file("output.txt", name)
+"#;
+    let captured = Captured(Rc::new(RefCell::new(Some(Vec::new()))));
+    let output = run_both("output", source, || Host {
+        input: Box::new(Cursor::new("Ada\n")),
+        output: Box::new(captured.clone()),
+        ..Host::new()
+    });
+    assert_eq!(output, "Ada");
+    let run = "name? Ada! hi\nname: VariableContent { value: \"Ada\", data_type: STRING }\n";
+    assert_eq!(captured.text(), run.repeat(2));
+}
+
+#[test]
+fn output_that_fails_is_an_io_error() {
+    let source = r#"
+try { print("lost") } catch error { write_file("output.txt", error.kind) }
+"#;
+    let closed = Captured(Rc::new(RefCell::new(None)));
+    let output = run_both("closed", source, || Host {
+        output: Box::new(closed.clone()),
+        ..Host::new()
+    });
+    assert_eq!(output, "io");
+}
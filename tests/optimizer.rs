@@ -0,0 +1,33 @@
+use std::process::Command;
+
+// The program the optimizer makes of a script.
+fn dump_optimized(file: &str) -> (String, bool) {
+    let output = Command::new(env!("CARGO_BIN_EXE_simple_script"))
+        .arg("--dump-optimized")
+        .arg(file)
+        .output()
+        .expect("Couldn't run simple_script.");
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    (stdout, output.status.success())
+}
+
+#[test]
+fn calls_too_big_to_fold_are_left_alone() {
+    let (program, success) = dump_optimized("tests/scripts/fold_limits.smpl");
+    assert!(success);
+    assert_eq!(
+        program,
+        "fn big() {\n    return format(\"{:99999999999999}\", 1)\n}\nprint(\"    1\")\n"
+    );
+}
+
+#[test]
+fn calls_to_functions_declared_anywhere_are_left_alone() {
+    let (program, success) = dump_optimized("tests/scripts/fold_declared.smpl");
+    assert!(success);
+    assert!(
+        program.ends_with("print(upper(\"a\"), lower(\"B\"))\n"),
+        "{}",
+        program
+    );
+}
@@ -0,0 +1,250 @@
+use std::process::{Command, Stdio};
+
+// The stdout and stderr of a script, which have to be the same with the VM.
+fn run(flags: &[&str], file: &str) -> (String, String) {
+    let run = |vm: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_simple_script"))
+            .args(vm)
+            .args(flags)
+            .arg(file)
+            .stdin(Stdio::null())
+            .output()
+            .expect("Couldn't run simple_script.");
+        let show = |output: &[u8]| String::from_utf8_lossy(output).into_owned();
+        (show(&output.stdout), show(&output.stderr))
+    };
+    let executed = run(&[]);
+    assert_eq!(executed, run(&["--vm"]), "output of {} with the VM", file);
+    executed
+}
+
+#[test]
+fn builtins_need_their_capabilities() {
+    let (stdout, stderr) = run(&["--allow=stdout"], "tests/scripts/permissions.smpl");
+    assert_eq!(
+        stdout,
+        "permission: Function input needs the capability 'stdin'.\n\
+         Function read_line needs the capability 'stdin'.\n\
+         Function env needs the capability 'env'.\n\
+         Function read_file needs the capability 'fs.read'.\n\
+         Function write_file needs the capability 'fs.write'.\n\
+         Function time.now needs the capability 'time'.\n\
+         Function random.rand_int needs the capability 'random'.\n\
+         shown: VariableContent { value: \"shown\", data_type: STRING }\n\
+         PURE BUILTINS STILL WORK\n"
+    );
+    assert_eq!(stderr, "");
+
+    let (stdout, stderr) = run(&["--allow=stdin"], "tests/scripts/stdin_only.smpl");
+    assert_eq!(stdout, "");
+    assert_eq!(
+        stderr,
+        "tests/scripts/stdin_only.smpl:5:37: Uncaught thrown error: Function input needs the \
+         capability 'stdout'. Statement debug.var needs the capability 'stdout'.\n"
+    );
+}
+
+#[test]
+fn constants_can_only_be_shadowed() {
+    let (stdout, stderr) = run(&[], "tests/scripts/constants.smpl");
+    assert_eq!(
+        stdout,
+        "4 10\n\
+         type: Variable count is of type INT, can't assign a value of type STRING.\n\
+         constant: Can't declare function 'print' as it is a builtin.\n\
+         count: INT: VariableContent { value: \"1\", data_type: INT }\n\
+         limit (const): VariableContent { value: \"10\", data_type: INT }\n"
+    );
+    assert_eq!(stderr, "");
+
+    let (stdout, stderr) = run(&[], "tests/scripts/constant_errors.smpl");
+    assert_eq!(stdout, "");
+    assert_eq!(
+        stderr,
+        "tests/scripts/constant_errors.smpl:6:1: Can't reassign constant 'limit'.\n    \
+         limit = 11\n    \
+         ^^^^^^^^^^\n\
+         tests/scripts/constant_errors.smpl:7:1: Can't set a field of constant 'origin'.\n    \
+         origin.x = 1\n    \
+         ^^^^^^^^^^^^\n\
+         tests/scripts/constant_errors.smpl:8:21: Can't reassign constant 'limit'.\n    \
+         var change = fn() { limit = 12 }\n                        \
+         ^^^^^^^^^^\n\
+         tests/scripts/constant_errors.smpl:9:1: Can't redeclare constant 'limit'.\n    \
+         const limit = 13\n    \
+         ^^^^^^^^^^^^^^^^\n"
+    );
+}
+
+#[test]
+fn closures_share_what_they_capture() {
+    let (stdout, stderr) = run(&[], "tests/scripts/closures.smpl");
+    assert_eq!(
+        stdout,
+        "2 3\n\
+         1\n\
+         1 2\n\
+         hi!!\n\
+         true false\n\
+         type: Variable number is of type INT and can't be called.\n\
+         type: Variable f is of type INT and can't be called.\n\
+         argument: Function <fn> is missing arguments for value.\n"
+    );
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn list_builtins_call_the_functions_they_get() {
+    let (stdout, stderr) = run(&[], "tests/scripts/higher_order.smpl");
+    assert_eq!(
+        stdout,
+        "[\"<4>\", \"<1>\", \"<3>\", \"<2>\"]\n\
+         [4, 3] []\n\
+         10 cba\n\
+         false true true false\n\
+         true false\n\
+         [\"a\", \"b\", \"c\"] [4, 3, 2, 1]\n\
+         [[0, \"x\"], [1, \"y\"]]\n\
+         [[1, \"a\"], [2, \"b\"]] [[1, true, \"z\"]]\n\
+         value: Can't reduce an empty list without an initial value.\n\
+         unknown_function: Unknown function 'missing'.\n\
+         type: Expected a function or the name of one, got INT.\n\
+         type: Function filter expects a BOOL, got INT.\n\
+         type: Can't compare STRING with INT.\n\
+         type: The comparator of sort should return a number, got STRING.\n\
+         argument: Function <fn> is missing arguments for b.\n"
+    );
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn arguments_are_bound_by_position_keyword_and_default() {
+    let (stdout, stderr) = run(&[], "tests/scripts/parameters.smpl");
+    assert_eq!(
+        stdout,
+        "hi ada []\n\
+         hey [1, 2]\n\
+         yo []\n\
+         [1, 1] [1, 2]\n\
+         1 5\n\
+         argument: Function describe is missing arguments for name.\n\
+         argument: Function describe got more than one value for name.\n\
+         argument: Function describe has no parameter named colour.\n\
+         argument: Function <fn> takes at most 2 arguments but got 3.\n\
+         type: Argument count of function typed should be of type INT, got STRING.\n\
+         argument: Function typed got more than one value for count.\n"
+    );
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn structs_check_their_fields() {
+    let (stdout, stderr) = run(&[], "tests/scripts/structs.smpl");
+    assert_eq!(
+        stdout,
+        "Point { x: 1, y: 2 } Named { name: \"a\", point: Point { x: 5, y: 2 } }\n\
+         5 true\n\
+         false [Point { x: 1, y: 2 }, Point { x: 1, y: 2 }]\n\
+         named: VariableContent { value: \"Named { name: \\\"a\\\", point: Point { x: 5, y: 2 } \
+         }\", data_type: STRUCT }\n\
+         point: VariableContent { value: \"Point { x: 1, y: 2 }\", data_type: STRUCT }\n\
+         type: Field x of struct Point should be of type INT, got STRING.\n\
+         type: Field y of struct Point should be of type INT, got STRING.\n\
+         field: Struct Point has no field z.\n\
+         field: Struct Point has no field z.\n"
+    );
+    assert_eq!(stderr, "");
+
+    let (stdout, stderr) = run(&[], "tests/scripts/struct_errors.smpl");
+    assert_eq!(stdout, "");
+    assert_eq!(
+        stderr,
+        "tests/scripts/struct_errors.smpl:4:15: Struct 'Point' is missing the fields y.\n    \
+         var missing = Point { x: 1 }\n                  \
+         ^^^^^^^^^^^^^^\n\
+         tests/scripts/struct_errors.smpl:5:33: Struct 'Point' has no field 'z'.\n    \
+         var extra = Point { x: 1, y: 2, z: 3 }\n                                    \
+         ^^^^\n\
+         tests/scripts/struct_errors.smpl:6:15: Unknown struct 'Missing'.\n    \
+         var unknown = Missing { x: 1 }\n                  \
+         ^^^^^^^^^^^^^^^^\n"
+    );
+}
+
+#[test]
+fn caught_errors_have_their_kind_and_location() {
+    let (stdout, stderr) = run(&[], "tests/scripts/catch.smpl");
+    assert_eq!(
+        stdout,
+        "thrown plain plain 3:5\n\
+         [1, 2] [1, 2] tests/scripts/catch.smpl\n\
+         Error { kind: \"type\", message: \"Variable n is of type INT, can't assign a value of \
+         type STRING.\", value: null, file: \"tests/scripts/catch.smpl\", line: 11, column: 5 }\n\
+         inner finally\n\
+         rethrown thrown inner from line 3\n\
+         first finally\n\
+         second finally\n\
+         1\n\
+         no error\n\
+         finally alone\n\
+         runs before the error\n"
+    );
+    assert_eq!(
+        stderr,
+        "tests/scripts/catch.smpl:29:7: Uncaught thrown error: last\n"
+    );
+}
+
+#[test]
+fn imports_run_once_and_export_functions_and_constants() {
+    let (stdout, stderr) = run(&[], "tests/scripts/imports.smpl");
+    assert_eq!(
+        stdout,
+        "counter.smpl ran\n\
+         #1 #2 #3\n\
+         # [\"#1\", \"#2\"]\n\
+         field: Module tests/scripts/modules/counter.smpl has no function or constant count.\n\
+         field: Module tests/scripts/modules/counter.smpl has no function or constant missing.\n"
+    );
+    assert_eq!(stderr, "");
+
+    let (stdout, stderr) = run(&[], "tests/scripts/import_errors.smpl");
+    assert_eq!(stdout, "");
+    assert_eq!(
+        stderr,
+        "tests/scripts/import_errors.smpl:2:1: Couldn't find the file \
+         'tests/scripts/modules/absent.smpl' to import.\n    \
+         import \"modules/absent.smpl\" as absent\n    \
+         ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^\n\
+         tests/scripts/modules/cycle_b.smpl:1:1: Circular import: \
+         tests/scripts/modules/cycle_a.smpl -> tests/scripts/modules/cycle_b.smpl -> \
+         tests/scripts/modules/cycle_a.smpl.\n    \
+         import \"cycle_a.smpl\" as a\n    \
+         ^^^^^^^^^^^^^^^^^^^^^^^^^^\n"
+    );
+}
+
+#[test]
+fn format_specs_pad_align_and_convert() {
+    let (stdout, stderr) = run(&[], "tests/scripts/format.smpl");
+    assert_eq!(
+        stdout,
+        "[***mid***] [l    ] [    r] [text ] [   42]\n\
+         [+0] [+2.2] [-003.142] [+00012] [-10.0--]\n\
+         ff FF 10 101 0xff 0o10 0b101 0x00ff -1\n\
+         2 1.000 lo\n\
+         \"q\\\"\\t\" 1.0 true      \"a\"\n\
+         aba a b\n\
+         {} {in} }{\n  \
+         2|1  |\n\
+         argument: Format \"{}\" needs a value at position 0 but got 0 values.\n\
+         argument: Format \"{2}\" needs a value at position 2 but got 1 values.\n\
+         argument: Format has no struct with a field name.\n\
+         value: Invalid format \"{\": a '{' isn't closed.\n\
+         value: Invalid format \"}\": a '}' isn't opened, write '}}' for one.\n\
+         type: Format type 'x' expects an INT, got FLOAT.\n\
+         value: Invalid format \"{:y}\": unknown format spec 'y'.\n\
+         value: Invalid format \"{:.99999}\": a precision can be 65535 at most.\n"
+    );
+    assert_eq!(stderr, "");
+}
@@ -0,0 +1,104 @@
+use std::fs;
+use std::process::{Command, Output, Stdio};
+
+// Runs a script with the flags, the seed and the fake clock make the output of every run the same.
+fn run(flags: &[&str], file: &str) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_simple_script"))
+        .args(["--seed=1", "--fake-clock=0"])
+        .args(flags)
+        .arg(file)
+        .stdin(Stdio::null())
+        .output()
+        .expect("Couldn't run simple_script.")
+}
+
+// The output of the executor and the VM, which have to be the same.
+fn assert_same(flags: &[&str], file: &str) {
+    let executor = run(flags, file);
+    let mut vm_flags = vec!["--vm"];
+    vm_flags.extend_from_slice(flags);
+    let vm = run(&vm_flags, file);
+    let show = |output: &[u8]| String::from_utf8_lossy(output).into_owned();
+    assert_eq!(
+        show(&executor.stdout),
+        show(&vm.stdout),
+        "stdout of {}",
+        file
+    );
+    assert_eq!(
+        show(&executor.stderr),
+        show(&vm.stderr),
+        "stderr of {}",
+        file
+    );
+    assert_eq!(
+        executor.status.code(),
+        vm.status.code(),
+        "exit code of {}",
+        file
+    );
+}
+
+#[test]
+fn examples_give_the_same_output_with_the_vm() {
+    let mut files: Vec<String> = fs::read_dir("examples")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "smpl")
+        })
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+    files.sort();
+    assert!(!files.is_empty());
+    for file in &files {
+        assert_same(&[], file);
+    }
+}
+
+#[test]
+fn limits_give_the_same_errors_with_the_vm() {
+    let file = "tests/scripts/instruction_limit.smpl";
+    assert_same(&["--max-instructions=1000"], file);
+    let output = run(&["--max-instructions=1000"], file);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "instruction_limit\nafter\n"
+    );
+    assert_same(&["--max-memory=100000"], "tests/scripts/memory_limit.smpl");
+    assert_same(&["--timeout=200"], "tests/scripts/sleep_limit.smpl");
+    assert_same(
+        &["--max-call-depth=100000"],
+        "tests/scripts/call_depth.smpl",
+    );
+}
+
+#[test]
+fn nested_values_count_every_level() {
+    let file = "tests/scripts/nested_memory.smpl";
+    assert_same(&["--max-memory=20000"], file);
+    let output = run(&["--max-memory=20000"], file);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "memory_limit\n163\nmemory_limit\n"
+    );
+}
+
+#[test]
+fn numbers_compare_the_same_with_the_vm() {
+    let file = "tests/scripts/numbers.smpl";
+    assert_same(&[], file);
+    let output = run(&["--vm"], file);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "3 -1.5 0.30000000000000004 2.5\n\
+         false false false false\n\
+         false false false\n\
+         true false false\n\
+         9223372036854776000 true 0\n\
+         typed\n\
+         type: Argument can't be of type 'STRING'.\n\
+         argument: Length of args was 1; there should atleast be 2 arguments.\n"
+    );
+}